@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy::math::curve::{Curve, EaseFunction};
+use bevy::transform::TransformSystem;
+use super::editor::Carried;
+use super::gameplay::Draggable;
+use super::pieces::PieceType;
+
+// Seconds a move takes at speed 1.
+const BASE_DURATION: f32 = 0.25;
+
+pub struct AnimationPlugin;
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(AnimationSettings::from_args().unwrap_or_default())
+            .add_systems(First, restore_targets)
+            .add_systems(PostUpdate, animate_pieces.before(TransformSystem::TransformPropagate));
+    }
+}
+
+// Speed 0 turns animations off, so pieces jump straight to their squares.
+#[derive(Resource, Clone, Copy)]
+pub struct AnimationSettings {
+    pub speed: f32,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings { speed: 1.0 }
+    }
+}
+
+impl AnimationSettings {
+    // `--animation-speed <speed>` on the command line, e.g. `--animation-speed 0`.
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let index = args.iter().position(|arg| arg == "--animation-speed")?;
+        let speed: f32 = args.get(index + 1)?.parse().ok()?;
+        if speed >= 0.0 { Some(AnimationSettings { speed }) } else { None }
+    }
+}
+
+// Gameplay always moves a piece's transform straight to where it belongs.
+// During PostUpdate the transform is swapped for an eased in-between one to
+// draw, and the real one is put back at the start of the next frame, so
+// hit-testing and the rules never see a piece halfway along.
+#[derive(Component)]
+pub struct Animated {
+    from: Transform,
+    to: Transform,
+    elapsed: f32,
+}
+
+impl Animated {
+    // Eased in-between translation and scale; rotation is left to the orientation module.
+    fn show(&self, transform: &mut Transform, duration: f32) {
+        let t = EaseFunction::CubicOut.sample_clamped(self.elapsed / duration);
+        transform.translation = self.from.translation.lerp(self.to.translation, t);
+        transform.scale = self.from.scale.lerp(self.to.scale, t);
+    }
+
+    fn is_done(&self, duration: f32) -> bool {
+        self.elapsed >= duration
+    }
+}
+
+fn restore_targets(mut piece_query: Query<(&mut Transform, &Animated)>) {
+    for (mut transform, animated) in piece_query.iter_mut() {
+        transform.translation = animated.to.translation;
+        transform.scale = animated.to.scale;
+    }
+}
+
+// A piece with its animation, if any, and whether the mouse is holding it.
+type AnimatedPiece = (Entity, &'static mut Transform, Option<&'static mut Animated>, Has<Draggable>, Has<Carried>);
+
+fn animate_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AnimationSettings>,
+    mut piece_query: Query<AnimatedPiece, With<PieceType>>,
+) {
+    let duration = BASE_DURATION / settings.speed;
+    for (piece, mut transform, animated, dragging, carried) in piece_query.iter_mut() {
+        let target = *transform;
+
+        // New pieces, e.g. after a reset, grow in from nothing.
+        let Some(mut animated) = animated else {
+            let animated = Animated {
+                from: target.with_scale(Vec3::ZERO),
+                to: target,
+                elapsed: 0.0,
+            };
+            if settings.speed > 0.0 {
+                animated.show(&mut transform, duration);
+            }
+            commands.entity(piece).insert(animated);
+            continue;
+        };
+
+        // Pieces in hand follow the cursor without lag.
+        if settings.speed <= 0.0 || dragging || carried {
+            *animated = Animated { from: target, to: target, elapsed: duration };
+            continue;
+        }
+
+        if target.translation != animated.to.translation || target.scale != animated.to.scale {
+            let mut shown = target;
+            animated.show(&mut shown, duration);
+            *animated = Animated { from: shown, to: target, elapsed: 0.0 };
+        }
+        else if !animated.is_done(duration) {
+            animated.elapsed += time.delta_secs();
+        }
+        animated.show(&mut transform, duration);
+    }
+}
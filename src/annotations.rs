@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use super::cursor::*;
+use super::editor::not_editing;
+use super::gameplay::MoveMade;
+use super::position::*;
+use super::reset::NewGame;
+
+const LINE_WIDTH: f32 = 6.0;
+const CIRCLE_RADIUS: f32 = 44.0;
+
+pub struct AnnotationsPlugin;
+impl Plugin for AnnotationsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Annotations>()
+            .add_systems(Startup, setup_gizmos)
+            .add_systems(Update, (
+                draw_annotation.after(update_cursor_pos).run_if(not_editing),
+                clear_annotations,
+                render_annotations,
+            ).chain());
+    }
+}
+
+// Plain right-drag draws green, Shift red, Alt blue and Ctrl yellow.
+const RED_KEYS: [KeyCode; 2] = [KeyCode::ShiftLeft, KeyCode::ShiftRight];
+const BLUE_KEYS: [KeyCode; 2] = [KeyCode::AltLeft, KeyCode::AltRight];
+const YELLOW_KEYS: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::ControlRight];
+// Every brush modifier, kept out of the key bindings.
+pub const BRUSH_KEYS: [KeyCode; 6] = [
+    RED_KEYS[0], RED_KEYS[1], BLUE_KEYS[0], BLUE_KEYS[1], YELLOW_KEYS[0], YELLOW_KEYS[1],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Brush {
+    Green,
+    Red,
+    Blue,
+    Yellow,
+}
+
+impl Brush {
+    fn from_keys(keys: &ButtonInput<KeyCode>) -> Self {
+        if keys.any_pressed(RED_KEYS) {
+            Brush::Red
+        }
+        else if keys.any_pressed(BLUE_KEYS) {
+            Brush::Blue
+        }
+        else if keys.any_pressed(YELLOW_KEYS) {
+            Brush::Yellow
+        }
+        else {
+            Brush::Green
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Brush::Green => Color::srgba(0.1, 0.6, 0.2, 0.8),
+            Brush::Red => Color::srgba(0.8, 0.1, 0.1, 0.8),
+            Brush::Blue => Color::srgba(0.1, 0.3, 0.8, 0.8),
+            Brush::Yellow => Color::srgba(0.9, 0.7, 0.0, 0.8),
+        }
+    }
+
+    // Colour letter used by the PGN `[%cal]` and `[%csl]` commands.
+    fn letter(self) -> char {
+        match self {
+            Brush::Green => 'G',
+            Brush::Red => 'R',
+            Brush::Blue => 'B',
+            Brush::Yellow => 'Y',
+        }
+    }
+}
+
+// Arrows and circled squares drawn on the current position.
+#[derive(Resource, Default)]
+pub struct Annotations {
+    pub arrows: Vec<(Square, Square, Brush)>,
+    pub circles: Vec<(Square, Brush)>,
+    drag_start: Option<Square>,
+}
+
+impl Annotations {
+    // Drawing the same mark again rubs it out.
+    fn toggle<T: PartialEq>(marks: &mut Vec<T>, mark: T) {
+        match marks.iter().position(|existing| *existing == mark) {
+            Some(index) => { marks.remove(index); }
+            None => marks.push(mark),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.circles.is_empty()
+    }
+
+    // PGN comment holding the marks, e.g. "[%csl Rd4][%cal Ge2e4]".
+    pub fn pgn_comment(&self) -> String {
+        let mut comment = String::new();
+        if !self.circles.is_empty() {
+            let circles: Vec<String> = self.circles
+                .iter()
+                .map(|(square, brush)| format!("{}{}", brush.letter(), square_name(*square)))
+                .collect();
+            comment += &format!("[%csl {}]", circles.join(","));
+        }
+        if !self.arrows.is_empty() {
+            let arrows: Vec<String> = self.arrows
+                .iter()
+                .map(|(from, to, brush)| format!("{}{}{}", brush.letter(), square_name(*from), square_name(*to)))
+                .collect();
+            comment += &format!("[%cal {}]", arrows.join(","));
+        }
+        comment
+    }
+}
+
+fn setup_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<DefaultGizmoConfigGroup>();
+    config.line.width = LINE_WIDTH;
+}
+
+// Right-drag between two squares draws an arrow, a right-click circles a square.
+fn draw_annotation(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_coords: Res<MouseWorldCoords>,
+    placement: Res<Placement>,
+    mut annotations: ResMut<Annotations>,
+) {
+    let square = mouse_coords.0.and_then(|pos| placement.square_at(pos));
+    if buttons.just_pressed(MouseButton::Right) {
+        annotations.drag_start = square;
+    }
+    if !buttons.just_released(MouseButton::Right) {
+        return;
+    }
+
+    let (Some(from), Some(to)) = (annotations.drag_start.take(), square) else {
+        return;
+    };
+    let brush = Brush::from_keys(&keys);
+    if from == to {
+        Annotations::toggle(&mut annotations.circles, (to, brush));
+    }
+    else {
+        Annotations::toggle(&mut annotations.arrows, (from, to, brush));
+    }
+}
+
+fn clear_annotations(
+    mut moves: EventReader<MoveMade>,
+    mut new_game: EventReader<NewGame>,
+    mut annotations: ResMut<Annotations>,
+) {
+    if moves.read().count() + new_game.read().count() > 0 && !annotations.is_empty() {
+        annotations.arrows.clear();
+        annotations.circles.clear();
+    }
+}
+
+fn render_annotations(
+    annotations: Res<Annotations>,
+    placement: Res<Placement>,
+    mut gizmos: Gizmos,
+) {
+    for (square, brush) in annotations.circles.iter() {
+        gizmos.circle_2d(placement.square_center(*square), CIRCLE_RADIUS, brush.color());
+    }
+    for (from, to, brush) in annotations.arrows.iter() {
+        gizmos
+            .arrow_2d(placement.square_center(*from), placement.square_center(*to), brush.color())
+            .with_tip_length(TILE_SIZE / 3.0);
+    }
+}
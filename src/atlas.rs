@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use bevy::asset::{LoadState, RenderAssetUsages};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use super::pieces::*;
+use super::theme::Theme;
+
+// Order of the piece images in the atlas: a set for each side, in turn.
+const SIDE_ORDER: [Side; 2] = [Side::White, Side::Black];
+const PIECE_ORDER: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Pawn,
+];
+// Stands in for a piece image that could not be loaded.
+const PLACEHOLDER_SIZE: u32 = 64;
+const PLACEHOLDER_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+pub struct AtlasPlugin;
+impl Plugin for AtlasPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PieceAtlas>()
+            .add_systems(Update, (load_piece_set, build_piece_atlas, draw_pieces).chain());
+    }
+}
+
+// Sprite of a piece that is not in play, e.g. in the editor palette or a profile.
+#[derive(Component, Clone, Copy)]
+pub struct PieceIcon {
+    pub piece: PieceType,
+    pub side: Side,
+}
+
+// The active theme's twelve piece images packed into one texture. Pieces
+// pick their image by index, so promotions and theme switches never load
+// anything per piece.
+#[derive(Resource, Default)]
+pub struct PieceAtlas {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+    indices: [usize; 12],
+    sources: Vec<Handle<Image>>,
+    pending: bool,
+}
+
+impl PieceAtlas {
+    // Looked up in the same tables the atlas is filled from, so the
+    // declaration order of the enums does not matter.
+    fn slot(piece: PieceType, side: Side) -> usize {
+        let side_index = SIDE_ORDER.iter().position(|s| *s == side).unwrap_or(0);
+        let piece_index = PIECE_ORDER.iter().position(|p| *p == piece).unwrap_or(0);
+        side_index * PIECE_ORDER.len() + piece_index
+    }
+
+    pub fn sprite(&self, piece: PieceType, side: Side) -> Sprite {
+        Sprite::from_atlas_image(
+            self.image.clone(),
+            TextureAtlas {
+                layout: self.layout.clone(),
+                index: self.indices[PieceAtlas::slot(piece, side)],
+            },
+        )
+    }
+}
+
+// Starts loading the images of a new theme, or repacks when one of the
+// current images is edited on disk.
+fn load_piece_set(
+    theme: Res<Theme>,
+    asset_server: Res<AssetServer>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut atlas: ResMut<PieceAtlas>,
+) {
+    let atlas = atlas.bypass_change_detection();
+    let edited = image_events.read().any(|event| match event {
+        AssetEvent::Modified { id } => atlas.sources.iter().any(|source| source.id() == *id),
+        _ => false,
+    });
+
+    if theme.is_changed() {
+        atlas.sources = SIDE_ORDER
+            .into_iter()
+            .flat_map(|side| PIECE_ORDER.map(|piece| asset_server.load(theme.piece_image(piece, side))))
+            .collect();
+        atlas.pending = true;
+    }
+    else if edited {
+        atlas.pending = true;
+    }
+}
+
+// Packs the images once all of them have loaded or failed to.
+fn build_piece_atlas(
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut atlas: ResMut<PieceAtlas>,
+) {
+    if !atlas.pending {
+        return;
+    }
+    let settled = atlas.sources.iter().all(|source| {
+        matches!(asset_server.load_state(source.id()), LoadState::Loaded | LoadState::Failed(_))
+    });
+    if !settled {
+        return;
+    }
+
+    let placeholder = Image::new_fill(
+        Extent3d {
+            width: PLACEHOLDER_SIZE,
+            height: PLACEHOLDER_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &PLACEHOLDER_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    let built = {
+        let mut builder = TextureAtlasBuilder::default();
+        builder.padding(UVec2::splat(2));
+        for source in atlas.sources.iter() {
+            match images.get(source) {
+                Some(image) => builder.add_texture(Some(source.id()), image),
+                None => builder.add_texture(Some(AssetId::invalid()), &placeholder),
+            };
+        }
+        builder.build()
+    };
+
+    atlas.pending = false;
+    match built {
+        Ok((layout, sources, image)) => {
+            let indices = std::array::from_fn(|slot| {
+                sources
+                    .texture_index(atlas.sources[slot].id())
+                    .or_else(|| sources.texture_index(AssetId::<Image>::invalid()))
+                    .unwrap_or(0)
+            });
+            atlas.indices = indices;
+            atlas.image = images.add(image);
+            atlas.layout = layouts.add(layout);
+        }
+        Err(err) => error!("Could not pack the piece images: {}", err),
+    }
+}
+
+// Redraws pieces whose type or side changed, and everything after a repack.
+fn draw_pieces(
+    atlas: Res<PieceAtlas>,
+    mut piece_query: Query<(Ref<PieceType>, Has<WhitePiece>, &mut Sprite)>,
+    mut icon_query: Query<(Ref<PieceIcon>, &mut Sprite), Without<PieceType>>,
+) {
+    for (piece, is_white, mut sprite) in piece_query.iter_mut() {
+        if atlas.is_changed() || piece.is_changed() {
+            let side = if is_white { Side::White } else { Side::Black };
+            *sprite = atlas.sprite(*piece, side);
+        }
+    }
+    for (icon, mut sprite) in icon_query.iter_mut() {
+        if atlas.is_changed() || icon.is_changed() {
+            *sprite = atlas.sprite(icon.piece, icon.side);
+        }
+    }
+}
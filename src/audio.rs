@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use bevy::asset::LoadState;
+use bevy::audio::Volume;
+use super::controls::*;
+use super::explosions::explode;
+use super::gameplay::{Castled, MoveMade, PieceCaptured};
+use super::highlights::{track_moves, LastMove};
+use super::pieces::Promoted;
+use super::variants::{judge_move, ActiveVariant};
+
+const VOLUME_STEP: f32 = 0.1;
+
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SoundSettings>()
+            .add_systems(Startup, load_sounds)
+            .add_systems(
+                Update,
+                (sound_keys, play_sounds.after(track_moves).after(judge_move).after(explode)),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Copy)]
+pub struct SoundSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings { volume: 0.5, muted: false }
+    }
+}
+
+#[derive(Resource)]
+struct Sounds {
+    moved: Handle<AudioSource>,
+    capture: Handle<AudioSource>,
+    castle: Handle<AudioSource>,
+    check: Handle<AudioSource>,
+    promotion: Handle<AudioSource>,
+    game_over: Handle<AudioSource>,
+}
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Sounds {
+        moved: asset_server.load("sounds/move.ogg"),
+        capture: asset_server.load("sounds/capture.ogg"),
+        castle: asset_server.load("sounds/castle.ogg"),
+        check: asset_server.load("sounds/check.ogg"),
+        promotion: asset_server.load("sounds/promotion.ogg"),
+        game_over: asset_server.load("sounds/game_over.ogg"),
+    });
+}
+
+// S mutes, - and = turn the volume down and up.
+fn sound_keys(keys: Res<ButtonInput<KeyCode>>, bindings: Res<Bindings>, mut settings: ResMut<SoundSettings>) {
+    if bindings.just_pressed(&keys, Action::Mute) {
+        settings.muted = !settings.muted;
+        info!("Sound {}", if settings.muted { "muted" } else { "on" });
+    }
+    if bindings.just_pressed(&keys, Action::VolumeDown) {
+        settings.volume = (settings.volume - VOLUME_STEP).max(0.0);
+        info!("Volume {:.0}%", settings.volume * 100.0);
+    }
+    if bindings.just_pressed(&keys, Action::VolumeUp) {
+        settings.volume = (settings.volume + VOLUME_STEP).min(1.0);
+        info!("Volume {:.0}%", settings.volume * 100.0);
+    }
+}
+
+// Plays the most important sound of the frame. Sounds that failed to load,
+// e.g. a missing file, are skipped, and without an audio device Bevy simply
+// plays nothing. Runs once the move has been checked for check and for the
+// end of the game, so a mating move plays only the game over sound.
+#[allow(clippy::too_many_arguments)]
+fn play_sounds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    sounds: Res<Sounds>,
+    settings: Res<SoundSettings>,
+    variant: Res<ActiveVariant>,
+    last_move: Res<LastMove>,
+    mut moves: EventReader<MoveMade>,
+    mut captures: EventReader<PieceCaptured>,
+    mut castles: EventReader<Castled>,
+    promoted_query: Query<(), Added<Promoted>>,
+    mut game_over: Local<bool>,
+) {
+    let moved = moves.read().count() > 0;
+    let captured = captures.read().count() > 0;
+    let castled = castles.read().count() > 0;
+    let finished = variant.result.is_some() && !*game_over;
+    *game_over = variant.result.is_some();
+
+    let sound = if finished {
+        &sounds.game_over
+    }
+    else if last_move.is_changed() && !last_move.checked.is_empty() {
+        &sounds.check
+    }
+    else if !promoted_query.is_empty() {
+        &sounds.promotion
+    }
+    else if castled {
+        &sounds.castle
+    }
+    else if captured {
+        &sounds.capture
+    }
+    else if moved {
+        &sounds.moved
+    }
+    else {
+        return;
+    };
+
+    if settings.muted || matches!(asset_server.load_state(sound.id()), LoadState::Failed(_)) {
+        return;
+    }
+    commands.spawn((
+        AudioPlayer::new(sound.clone()),
+        PlaybackSettings::DESPAWN.with_volume(Volume::Linear(settings.volume)),
+    ));
+}
@@ -1,31 +1,113 @@
 use bevy::prelude::*; 
+use bevy::window::PrimaryWindow;
 use bevy_ecs_tilemap::prelude::*;
+use super::position::TILE_SIZE;
+use super::theme::Theme;
 
 const MAP_LENGTH: u32 = 8;
+// Largest board the square names can cover, one letter per file.
+const MAX_LENGTH: u32 = 26;
+// The back rank always has eight pieces, and each side needs its back rank
+// and pawn rank.
+const MIN_WIDTH: u32 = 8;
+const MIN_HEIGHT: u32 = 4;
+// Room kept around the board for the capture zones, profiles and editor palette.
+const SIDE_MARGIN: f32 = 300.0;
+const TOP_MARGIN: f32 = 140.0;
+
+// Columns and rows of the board, set with `--board <width>x<height>`, e.g. 10x8.
+#[derive(Resource, Clone, Copy)]
+pub struct BoardSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize {
+            width: MAP_LENGTH,
+            height: MAP_LENGTH,
+        }
+    }
+}
+
+impl BoardSize {
+    // Falls back to 8x8, with an error, for a size that cannot be played on.
+    fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let index = args.iter().position(|arg| arg == "--board")?;
+        let parsed = args.get(index + 1).and_then(|arg| {
+            let (width, height) = arg.split_once('x')?;
+            Some(BoardSize {
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+            })
+        });
+        let Some(size) = parsed else {
+            error!("--board expects <width>x<height>, e.g. 10x8; using 8x8");
+            return None;
+        };
+        if !(MIN_WIDTH..=MAX_LENGTH).contains(&size.width) || !(MIN_HEIGHT..=MAX_LENGTH).contains(&size.height) {
+            error!(
+                "--board {}x{}: the board needs {} to {} files and {} to {} ranks; using 8x8",
+                size.width, size.height, MIN_WIDTH, MAX_LENGTH, MIN_HEIGHT, MAX_LENGTH,
+            );
+            return None;
+        }
+        Some(size)
+    }
+
+    // World space taken up by the board and the margins around it.
+    pub fn world_size(&self) -> Vec2 {
+        Vec2::new(
+            self.width as f32 * TILE_SIZE + 2.0 * SIDE_MARGIN,
+            self.height as f32 * TILE_SIZE + 2.0 * TOP_MARGIN,
+        )
+    }
+}
+
+// Pixels per world unit, chosen so the board and its margins just fit the
+// window. The camera (see the view module) and the UI text both follow it,
+// so everything resizes together with the window.
+#[derive(Resource, Clone, Copy)]
+pub struct BoardScale(pub f32);
+
+impl Default for BoardScale {
+    fn default() -> Self {
+        BoardScale(1.0)
+    }
+}
 
 // Set that collects systems 
 #[derive(SystemSet, Clone, Copy, Hash, PartialEq, Eq, Debug)] 
 pub struct SpawnMapSet;
 
+// Set holding the system that works out the BoardScale.
+#[derive(SystemSet, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct FitSet;
+
 #[derive(Component)]
 pub struct MainCamera;
 
+
 fn startup(
     mut commands: Commands,
-    asset_server: Res<AssetServer>
+    asset_server: Res<AssetServer>,
+    size: Res<BoardSize>,
+    theme: Res<Theme>,
 ) {
-    // Spawn Camera
+    // Spawn Camera, zoomed and panned by the view module.
     commands.spawn((Camera2d, MainCamera));
 
     let image_handles = vec![
-        asset_server.load("dirt.png"),
-        asset_server.load("grass.png"),
+        asset_server.load(theme.image_path(&theme.dark_square)),
+        asset_server.load(theme.image_path(&theme.light_square)),
     ];
     let texture_vec = TilemapTexture::Vector(image_handles);
 
     let board_size = TilemapSize { 
-        x: MAP_LENGTH,
-        y: MAP_LENGTH,
+        x: size.width,
+        y: size.height,
     };
 
     let tilemap_entity = commands.spawn_empty().id(); 
@@ -48,7 +130,7 @@ fn startup(
         &mut tile_storage,
     );
 
-    let tile_size = TilemapTileSize {x: 100.0, y: 100.0};
+    let tile_size = TilemapTileSize {x: TILE_SIZE, y: TILE_SIZE};
     let grid_size = tile_size.into();
     let map_type = TilemapType::default();
 
@@ -152,11 +234,33 @@ fn fill_board_geo(
 }
 // END OF BOARD SETUP
 
+fn fit_to_window(
+    window_query: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    size: Res<BoardSize>,
+    mut scale: ResMut<BoardScale>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Ok(window) = window_query.single() else {
+        return;
+    };
+    let world = size.world_size();
+    let fit = (window.width() / world.x).min(window.height() / world.y);
+    if fit <= 0.0 {
+        return;
+    }
+
+    scale.0 = fit;
+    ui_scale.0 = fit;
+}
+
 pub struct BoardPlugin;
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app
         //    .init_resource::<TileHandleSquare>()
-            .add_systems(Startup, startup.in_set(SpawnMapSet));
+            .insert_resource(BoardSize::from_args().unwrap_or_default())
+            .init_resource::<BoardScale>()
+            .add_systems(Startup, startup.in_set(SpawnMapSet))
+            .add_systems(Update, fit_to_window.in_set(FitSet));
     }
 }
\ No newline at end of file
@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+use super::annotations::Annotations;
+use super::controls::*;
+use super::orientation::Orientation;
+use super::pieces::*;
+use super::position::*;
+use super::reset::NewGame;
+use super::rules::*;
+
+// Number of the standard chess setup in the Chess960 numbering.
+const STANDARD_ID: u16 = 518;
+const POSITIONS: u16 = 960;
+
+// Knight placements among the five squares left after the bishops and queen.
+const KNIGHT_PATTERNS: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+];
+
+pub struct Chess960Plugin;
+impl Plugin for Chess960Plugin {
+    fn build(&self, app: &mut App) {
+        // `--chess960` starts a random Chess960 game, `--chess960 <id>` a given one.
+        let args: Vec<String> = std::env::args().collect();
+        if let Some(index) = args.iter().position(|arg| arg == "--chess960") {
+            let id = args
+                .get(index + 1)
+                .and_then(|id| id.parse::<u16>().ok())
+                .filter(|id| *id < POSITIONS)
+                .unwrap_or_else(random_id);
+            app.insert_resource(chess960_back_rank(id));
+        }
+
+        app.add_systems(Update, (new_game, log_fen));
+    }
+}
+
+// Pieces with what FEN output needs to know about them.
+type FenPiece = (&'static Transform, &'static PieceType, Has<WhitePiece>, Has<Unmoved>);
+
+fn random_id() -> u16 {
+    rand::random_range(0..POSITIONS)
+}
+
+// Back rank of the Chess960 starting position with the given number (0-959).
+pub fn chess960_back_rank(id: u16) -> BackRank {
+    let mut pieces: [Option<PieceType>; 8] = [None; 8];
+    let mut n = id as usize % POSITIONS as usize;
+
+    // Light-squared bishop on b, d, f or h, dark-squared bishop on a, c, e or g.
+    pieces[n % 4 * 2 + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    pieces[n % 4 * 2] = Some(PieceType::Bishop);
+    n /= 4;
+
+    let empty = |pieces: &[Option<PieceType>; 8]| -> Vec<usize> {
+        (0..8).filter(|i| pieces[*i].is_none()).collect()
+    };
+
+    let queen = empty(&pieces)[n % 6];
+    pieces[queen] = Some(PieceType::Queen);
+    n /= 6;
+
+    let free = empty(&pieces);
+    let (first, second) = KNIGHT_PATTERNS[n];
+    pieces[free[first]] = Some(PieceType::Knight);
+    pieces[free[second]] = Some(PieceType::Knight);
+
+    // The king always stands between the two rooks.
+    let free = empty(&pieces);
+    pieces[free[0]] = Some(PieceType::Rook);
+    pieces[free[1]] = Some(PieceType::King);
+    pieces[free[2]] = Some(PieceType::Rook);
+
+    BackRank {
+        pieces: pieces.map(|piece| piece.unwrap_or(PieceType::Pawn)),
+        chess960: if id == STANDARD_ID { None } else { Some(id) },
+    }
+}
+
+fn new_game(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut back_rank: ResMut<BackRank>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if bindings.just_pressed(&keys, Action::NewChess960) {
+        *back_rank = chess960_back_rank(random_id());
+        info!("Chess960 position {}", back_rank.chess960.unwrap_or(STANDARD_ID));
+        new_game.write(NewGame);
+    }
+    if bindings.just_pressed(&keys, Action::StandardGame) {
+        *back_rank = BackRank::default();
+        new_game.write(NewGame);
+    }
+}
+
+// Writes the current position to the log in X-FEN and Shredder-FEN, with any
+// arrows and circles as a PGN comment.
+fn log_fen(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    placement: Res<Placement>,
+    annotations: Res<Annotations>,
+    orientation: Res<Orientation>,
+    piece_query: Query<FenPiece, With<Movable>>,
+) {
+    if !bindings.just_pressed(&keys, Action::LogFen) {
+        return;
+    }
+
+    let board = BoardState::collect(
+        &placement,
+        piece_query.iter().map(|(transform, piece, is_white, _)| (transform, piece, is_white)),
+    );
+
+    // Unmoved rooks on the home rank of a side whose king is also unmoved.
+    let mut castling_rooks = Vec::new();
+    for side in [Side::White, Side::Black] {
+        let row = board.home_row(side);
+        let king_unmoved = piece_query.iter().any(|(transform, piece, is_white, unmoved)| {
+            unmoved && *piece == PieceType::King && is_white == (side == Side::White)
+                && placement.square_at(transform.translation.truncate()).is_some_and(|square| square.1 == row)
+        });
+        if !king_unmoved {
+            continue;
+        }
+        for (transform, piece, is_white, unmoved) in piece_query.iter() {
+            if !unmoved || *piece != PieceType::Rook || is_white != (side == Side::White) {
+                continue;
+            }
+            if let Some((col, rook_row)) = placement.square_at(transform.translation.truncate())
+                && rook_row == row
+            {
+                castling_rooks.push((side, col));
+            }
+        }
+    }
+
+    let side_to_move = orientation.side_to_move;
+    info!("X-FEN: {}", board.fen(side_to_move, &board.xfen_castling(&castling_rooks)));
+    info!("Shredder-FEN: {}", board.fen(side_to_move, &board.shredder_castling(&castling_rooks)));
+    if !annotations.is_empty() {
+        info!("Annotations: {{{}}}", annotations.pgn_comment());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letters(back_rank: &BackRank) -> String {
+        back_rank
+            .pieces
+            .iter()
+            .map(|piece| match piece {
+                PieceType::King => 'K',
+                PieceType::Queen => 'Q',
+                PieceType::Rook => 'R',
+                PieceType::Bishop => 'B',
+                PieceType::Knight => 'N',
+                PieceType::Pawn => 'P',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn standard_id_is_the_standard_setup() {
+        let back_rank = chess960_back_rank(STANDARD_ID);
+        assert_eq!(letters(&back_rank), "RNBQKBNR");
+        assert_eq!(back_rank.chess960, None);
+    }
+
+    #[test]
+    fn first_id() {
+        let back_rank = chess960_back_rank(0);
+        assert_eq!(letters(&back_rank), "BBQNNRKR");
+        assert_eq!(back_rank.chess960, Some(0));
+    }
+
+    #[test]
+    fn every_id_is_a_legal_setup() {
+        let mut seen = std::collections::HashSet::new();
+        for id in 0..POSITIONS {
+            let rank = letters(&chess960_back_rank(id));
+            let find = |letter| -> Vec<usize> {
+                rank.char_indices().filter(|(_, c)| *c == letter).map(|(i, _)| i).collect()
+            };
+            let (king, rooks, bishops) = (find('K'), find('R'), find('B'));
+            assert_eq!(king.len(), 1, "{}: {}", id, rank);
+            assert_eq!(find('Q').len(), 1, "{}: {}", id, rank);
+            assert_eq!(find('N').len(), 2, "{}: {}", id, rank);
+            assert!(rooks.len() == 2 && rooks[0] < king[0] && king[0] < rooks[1], "{}: {}", id, rank);
+            assert!(bishops.len() == 2 && bishops[0] % 2 != bishops[1] % 2, "{}: {}", id, rank);
+            assert!(seen.insert(rank), "{} repeats an earlier position", id);
+        }
+    }
+}
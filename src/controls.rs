@@ -0,0 +1,295 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use super::annotations::BRUSH_KEYS;
+
+// Keys that drive the settings menu. Escape also closes it, or cancels
+// rebinding there.
+pub const MENU_KEYS: [KeyCode; 5] = [
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::Escape,
+];
+
+// Everything that can be triggered from the keyboard.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Windowed,
+    Fullscreen,
+    SmallWindow,
+    MediumWindow,
+    LargeWindow,
+    ResetBoard,
+    PromoteQueen,
+    PromoteRook,
+    PromoteBishop,
+    PromoteKnight,
+    EditPosition,
+    PlayFromEditor,
+    SideToMove,
+    WhiteKingside,
+    WhiteQueenside,
+    BlackKingside,
+    BlackQueenside,
+    NewChess960,
+    StandardGame,
+    LogFen,
+    SwitchVariant,
+    FlipBoard,
+    AutoFlip,
+    Mute,
+    VolumeDown,
+    VolumeUp,
+    SwitchTheme,
+    ResetView,
+    Settings,
+}
+
+impl Action {
+    pub const ALL: [Action; 29] = [
+        Action::Windowed,
+        Action::Fullscreen,
+        Action::SmallWindow,
+        Action::MediumWindow,
+        Action::LargeWindow,
+        Action::ResetBoard,
+        Action::PromoteQueen,
+        Action::PromoteRook,
+        Action::PromoteBishop,
+        Action::PromoteKnight,
+        Action::EditPosition,
+        Action::PlayFromEditor,
+        Action::SideToMove,
+        Action::WhiteKingside,
+        Action::WhiteQueenside,
+        Action::BlackKingside,
+        Action::BlackQueenside,
+        Action::NewChess960,
+        Action::StandardGame,
+        Action::LogFen,
+        Action::SwitchVariant,
+        Action::FlipBoard,
+        Action::AutoFlip,
+        Action::Mute,
+        Action::VolumeDown,
+        Action::VolumeUp,
+        Action::SwitchTheme,
+        Action::ResetView,
+        Action::Settings,
+    ];
+
+    pub fn default_key(self) -> KeyCode {
+        match self {
+            Action::Windowed => KeyCode::Digit4,
+            Action::Fullscreen => KeyCode::Digit5,
+            Action::SmallWindow => KeyCode::Digit1,
+            Action::MediumWindow => KeyCode::Digit2,
+            Action::LargeWindow => KeyCode::Digit3,
+            Action::ResetBoard => KeyCode::Escape,
+            Action::PromoteQueen => KeyCode::KeyQ,
+            Action::PromoteRook => KeyCode::KeyR,
+            Action::PromoteBishop => KeyCode::KeyB,
+            Action::PromoteKnight => KeyCode::KeyK,
+            Action::EditPosition => KeyCode::KeyE,
+            Action::PlayFromEditor => KeyCode::Enter,
+            Action::SideToMove => KeyCode::Tab,
+            Action::WhiteKingside => KeyCode::F1,
+            Action::WhiteQueenside => KeyCode::F2,
+            Action::BlackKingside => KeyCode::F3,
+            Action::BlackQueenside => KeyCode::F4,
+            Action::NewChess960 => KeyCode::KeyN,
+            Action::StandardGame => KeyCode::KeyM,
+            Action::LogFen => KeyCode::KeyF,
+            Action::SwitchVariant => KeyCode::KeyV,
+            Action::FlipBoard => KeyCode::KeyX,
+            Action::AutoFlip => KeyCode::KeyZ,
+            Action::Mute => KeyCode::KeyS,
+            Action::VolumeDown => KeyCode::Minus,
+            Action::VolumeUp => KeyCode::Equal,
+            Action::SwitchTheme => KeyCode::KeyT,
+            Action::ResetView => KeyCode::KeyC,
+            Action::Settings => KeyCode::KeyO,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Windowed => "Windowed mode",
+            Action::Fullscreen => "Fullscreen",
+            Action::SmallWindow => "Small window",
+            Action::MediumWindow => "Medium window",
+            Action::LargeWindow => "Large window",
+            Action::ResetBoard => "Reset the board",
+            Action::PromoteQueen => "Promote to queen",
+            Action::PromoteRook => "Promote to rook",
+            Action::PromoteBishop => "Promote to bishop",
+            Action::PromoteKnight => "Promote to knight",
+            Action::EditPosition => "Edit the position",
+            Action::PlayFromEditor => "Play from the editor",
+            Action::SideToMove => "Editor: side to move",
+            Action::WhiteKingside => "Editor: white O-O",
+            Action::WhiteQueenside => "Editor: white O-O-O",
+            Action::BlackKingside => "Editor: black O-O",
+            Action::BlackQueenside => "Editor: black O-O-O",
+            Action::NewChess960 => "New Chess960 game",
+            Action::StandardGame => "New standard game",
+            Action::LogFen => "Log the FEN",
+            Action::SwitchVariant => "Switch variant",
+            Action::FlipBoard => "Flip the board",
+            Action::AutoFlip => "Auto-flip",
+            Action::Mute => "Mute",
+            Action::VolumeDown => "Volume down",
+            Action::VolumeUp => "Volume up",
+            Action::SwitchTheme => "Switch theme",
+            Action::ResetView => "Reset the view",
+            Action::Settings => "Settings menu",
+        }
+    }
+}
+
+// Keys bound to each action, stored with the settings. Actions missing from
+// the settings file keep their default key.
+#[derive(Resource, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Bindings {
+    keys: BTreeMap<Action, KeyCode>,
+}
+
+impl Bindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys.get(&action).copied().unwrap_or_else(|| action.default_key())
+    }
+
+    // The action already using a key, if any.
+    pub fn action_for(&self, key: KeyCode) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| self.key(*action) == key)
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.keys.insert(action, key);
+    }
+
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keys.just_pressed(self.key(action))
+    }
+
+    pub fn just_released(&self, keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keys.just_released(self.key(action))
+    }
+
+    pub fn pressed(&self, keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keys.pressed(self.key(action))
+    }
+
+    // Puts back the default key of any action whose key is reserved or
+    // already taken by another action, e.g. in a hand-edited settings file.
+    pub fn resolve_conflicts(&mut self) {
+        for action in Action::ALL {
+            let key = self.key(action);
+            if let Some(reason) = reserved_use(key).filter(|_| key != action.default_key()) {
+                warn!(
+                    "{} is reserved for {}, {} goes back to {}",
+                    key_name(key),
+                    reason,
+                    action.description(),
+                    key_name(action.default_key()),
+                );
+                self.keys.remove(&action);
+            }
+        }
+        // Defaults never clash, so at least one of each pair has a custom key
+        // and every pass leaves one fewer behind.
+        while let Some((first, second)) = self.first_clash() {
+            let reset = if self.key(second) != second.default_key() { second } else { first };
+            warn!(
+                "{} and {} share {}, {} goes back to {}",
+                first.description(),
+                second.description(),
+                key_name(self.key(first)),
+                reset.description(),
+                key_name(reset.default_key()),
+            );
+            self.keys.remove(&reset);
+        }
+    }
+
+    fn first_clash(&self) -> Option<(Action, Action)> {
+        Action::ALL.into_iter().enumerate().find_map(|(index, first)| {
+            Action::ALL[index + 1..]
+                .iter()
+                .find(|second| self.key(**second) == self.key(first))
+                .map(|second| (first, *second))
+        })
+    }
+
+    // Short name of the key bound to an action, e.g. "Q" or "1".
+    pub fn name(&self, action: Action) -> String {
+        key_name(self.key(action))
+    }
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+// What a key is used for outside the bindings, if anything.
+pub fn reserved_use(key: KeyCode) -> Option<&'static str> {
+    if MENU_KEYS.contains(&key) {
+        Some("the settings menu")
+    }
+    else if BRUSH_KEYS.contains(&key) {
+        Some("arrow and circle colours")
+    }
+    else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_key_goes_back_to_default() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Mute, KeyCode::KeyQ);
+        bindings.resolve_conflicts();
+        assert_eq!(bindings.key(Action::Mute), KeyCode::KeyS);
+        assert_eq!(bindings.key(Action::PromoteQueen), KeyCode::KeyQ);
+
+        // With two custom keys the later action gives way.
+        bindings.bind(Action::Mute, KeyCode::KeyJ);
+        bindings.bind(Action::SwitchTheme, KeyCode::KeyJ);
+        bindings.resolve_conflicts();
+        assert_eq!(bindings.key(Action::Mute), KeyCode::KeyJ);
+        assert_eq!(bindings.key(Action::SwitchTheme), KeyCode::KeyT);
+    }
+
+    #[test]
+    fn displaced_action_can_displace_another() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Mute, KeyCode::KeyC);
+        bindings.bind(Action::ResetView, KeyCode::KeyT);
+        bindings.resolve_conflicts();
+        // Reset view gives T back to the theme switch, then takes C from mute.
+        assert_eq!(bindings.key(Action::ResetView), KeyCode::KeyC);
+        assert_eq!(bindings.key(Action::SwitchTheme), KeyCode::KeyT);
+        assert_eq!(bindings.key(Action::Mute), KeyCode::KeyS);
+        assert_eq!(bindings.first_clash(), None);
+    }
+
+    #[test]
+    fn reserved_key_goes_back_to_default() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Mute, KeyCode::ArrowUp);
+        bindings.resolve_conflicts();
+        assert_eq!(bindings.key(Action::Mute), KeyCode::KeyS);
+        // A reserved key that is an action's own default stays bound.
+        assert_eq!(bindings.key(Action::ResetBoard), KeyCode::Escape);
+    }
+}
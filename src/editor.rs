@@ -0,0 +1,295 @@
+use bevy::prelude::*;
+use super::atlas::PieceIcon;
+use super::controls::*;
+use super::cursor::*;
+use super::gameplay::PIECESIZE;
+use super::pieces::*;
+use super::position::*;
+use super::rules::*;
+use super::variants::ActiveVariant;
+
+// Gap between the board's right edge and the palette.
+const PALETTE_GAP: f32 = 150.0;
+// Distance from the board's top edge down to the first palette row.
+const PALETTE_TOP: f32 = 150.0;
+const PALETTE_SCALE: f32 = 0.8;
+
+pub struct EditorPlugin;
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Editor>()
+            .add_systems(Update, (enter_editor, editor_keys, start_from_editor, update_status).chain())
+            .add_systems(
+                Update,
+                (pick_piece.after(update_cursor_pos), carry, place)
+                    .chain()
+                    .run_if(editing),
+            );
+    }
+}
+
+// Position setup that is being edited, kept after play starts from it.
+#[derive(Resource, Default)]
+pub struct Editor {
+    pub active: bool,
+    pub side_to_move: Side,
+    pub castling: CastlingRights,
+    message: String,
+}
+
+#[derive(Component)]
+struct PaletteEntry;
+
+#[derive(Component)]
+struct EditorStatus;
+
+#[derive(Component)]
+pub struct Carried;
+
+// Pieces on the board other than the one being carried.
+type Placed = (With<Movable>, Without<Carried>);
+// Palette and status text, removed when play starts.
+type EditorUi = Or<(With<PaletteEntry>, With<EditorStatus>)>;
+
+pub fn editing(editor: Res<Editor>) -> bool {
+    editor.active
+}
+
+pub fn not_editing(editor: Res<Editor>) -> bool {
+    !editor.active
+}
+
+fn under_cursor(pos: Vec2, transform: &Transform) -> bool {
+    (pos.x - transform.translation.x).abs() <= PIECESIZE
+        && (pos.y - transform.translation.y).abs() <= PIECESIZE
+}
+
+fn enter_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    placement: Res<Placement>,
+    mut variant: ResMut<ActiveVariant>,
+) {
+    if editor.active || !bindings.just_pressed(&keys, Action::EditPosition) {
+        return;
+    }
+    // The set up position is a new game, whatever became of the last one.
+    variant.rules.reset();
+    variant.result = None;
+    let palette_x = -placement.left_edge() + PALETTE_GAP;
+
+    let types = [
+        PieceType::King,
+        PieceType::Queen,
+        PieceType::Rook,
+        PieceType::Bishop,
+        PieceType::Knight,
+        PieceType::Pawn,
+    ];
+    for (column, side) in [Side::White, Side::Black].into_iter().enumerate() {
+        for (row, piece) in types.into_iter().enumerate() {
+            commands.spawn((
+                Sprite::default(),
+                Transform::from_xyz(
+                    palette_x + column as f32 * TILE_SIZE,
+                    -placement.bottom_edge() - PALETTE_TOP - row as f32 * TILE_SIZE,
+                    1.0,
+                ).with_scale(Vec3::splat(PALETTE_SCALE)),
+                PaletteEntry,
+                PieceIcon { piece, side },
+            ));
+        }
+    }
+
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            right: Val::Px(12.0),
+            ..default()
+        },
+        EditorStatus,
+    ));
+
+    editor.active = true;
+    editor.message.clear();
+}
+
+fn editor_keys(
+    mut editor: ResMut<Editor>,
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+) {
+    if !editor.active {
+        return;
+    }
+    if bindings.just_pressed(&keys, Action::SideToMove) {
+        editor.side_to_move = editor.side_to_move.opponent();
+    }
+    if bindings.just_pressed(&keys, Action::WhiteKingside) {
+        editor.castling.white_kingside = !editor.castling.white_kingside;
+    }
+    if bindings.just_pressed(&keys, Action::WhiteQueenside) {
+        editor.castling.white_queenside = !editor.castling.white_queenside;
+    }
+    if bindings.just_pressed(&keys, Action::BlackKingside) {
+        editor.castling.black_kingside = !editor.castling.black_kingside;
+    }
+    if bindings.just_pressed(&keys, Action::BlackQueenside) {
+        editor.castling.black_queenside = !editor.castling.black_queenside;
+    }
+}
+
+fn update_status(
+    editor: Res<Editor>,
+    bindings: Res<Bindings>,
+    mut status_query: Query<&mut Text, With<EditorStatus>>,
+) {
+    if !editor.is_changed() && !bindings.is_changed() {
+        return;
+    }
+    let castling_keys = [
+        Action::WhiteKingside,
+        Action::WhiteQueenside,
+        Action::BlackKingside,
+        Action::BlackQueenside,
+    ].map(|action| bindings.name(action));
+    for mut text in status_query.iter_mut() {
+        text.0 = format!(
+            "Board editor\n{:?} to move ({})\nCastling: {} ({})\nPress {} to play\n{}",
+            editor.side_to_move,
+            bindings.name(Action::SideToMove),
+            editor.castling.fen(),
+            castling_keys.join(", "),
+            bindings.name(Action::PlayFromEditor),
+            editor.message,
+        );
+    }
+}
+
+fn pick_piece(
+    mut commands: Commands,
+    cursor_pos: Res<MouseWorldCoords>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    palette_query: Query<(&Transform, &PieceIcon), With<PaletteEntry>>,
+    piece_query: Query<(Entity, &Transform), With<Movable>>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(pos) = cursor_pos.0 else {
+        return;
+    };
+
+    for (transform, entry) in palette_query.iter() {
+        if under_cursor(pos, transform) {
+            let piece = spawn_piece(&mut commands, entry.piece, entry.side, pos);
+            commands.entity(piece).insert(Carried);
+            return;
+        }
+    }
+
+    for (piece, transform) in piece_query.iter() {
+        if under_cursor(pos, transform) {
+            commands.entity(piece).insert(Carried);
+            return;
+        }
+    }
+}
+
+fn carry(
+    cursor_pos: Res<MouseWorldCoords>,
+    piece: Single<&mut Transform, With<Carried>>,
+) {
+    let mut transform = piece.into_inner();
+    if let Some(pos) = cursor_pos.0 {
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+        transform.translation.z = 2.0;
+    }
+}
+
+// Drops the carried piece onto a square, replacing whatever stood there.
+// Pieces let go of off the board are removed.
+fn place(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    placement: Res<Placement>,
+    carried_query: Single<(Entity, &mut Transform), With<Carried>>,
+    piece_query: Query<(Entity, &Transform), Placed>,
+) {
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let (carried, mut transform) = carried_query.into_inner();
+    commands.entity(carried).remove::<Carried>();
+
+    let Some(square) = placement.square_at(transform.translation.truncate()) else {
+        commands.entity(carried).despawn();
+        return;
+    };
+
+    for (piece, other) in piece_query.iter() {
+        if placement.square_at(other.translation.truncate()) == Some(square) {
+            commands.entity(piece).despawn();
+        }
+    }
+    let center = placement.square_center(square);
+    transform.translation = center.extend(1.0);
+}
+
+fn start_from_editor(
+    mut commands: Commands,
+    mut editor: ResMut<Editor>,
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    placement: Res<Placement>,
+    piece_query: Query<(Entity, &Transform, &PieceType, Has<WhitePiece>), With<Movable>>,
+    editor_query: Query<Entity, EditorUi>,
+) {
+    if !editor.active || !bindings.just_pressed(&keys, Action::PlayFromEditor) {
+        return;
+    }
+
+    let board = BoardState::collect(
+        &placement,
+        piece_query.iter().map(|(_, transform, piece, is_white)| (transform, piece, is_white)),
+    );
+    match board.validate(editor.side_to_move, &editor.castling) {
+        Ok(()) => {
+            info!("Starting from {}", board.fen(editor.side_to_move, &editor.castling.fen()));
+            // Only the king and rooks named in the castling rights may castle.
+            let rights = editor.castling;
+            for (piece, transform, piece_type, is_white) in piece_query.iter() {
+                let (kingside, queenside) = if is_white {
+                    (rights.white_kingside, rights.white_queenside)
+                }
+                else {
+                    (rights.black_kingside, rights.black_queenside)
+                };
+                let col = placement.square_at(transform.translation.truncate()).map(|(col, _)| col);
+                let can_castle = match piece_type {
+                    PieceType::King => kingside || queenside,
+                    PieceType::Rook => (kingside && col == Some(board.width - 1))
+                        || (queenside && col == Some(0)),
+                    _ => false,
+                };
+                if can_castle {
+                    commands.entity(piece).insert(Unmoved);
+                }
+                else {
+                    commands.entity(piece).remove::<Unmoved>();
+                }
+            }
+            for entity in editor_query.iter() {
+                commands.entity(entity).despawn();
+            }
+            editor.active = false;
+        }
+        Err(error) => editor.message = error.to_string(),
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+use super::gameplay::{MoveSet, PieceCaptured};
+use super::pieces::*;
+use super::position::*;
+use super::rules::*;
+use super::variants::ActiveVariant;
+
+const FLASH_SECONDS: f32 = 0.4;
+const SPARK_SECONDS: f32 = 0.6;
+const SPARK_SPEED: f32 = 250.0;
+const SPARK_SIZE: f32 = 8.0;
+const SPARKS: usize = 12;
+
+pub struct ExplosionsPlugin;
+impl Plugin for ExplosionsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (explode.after(MoveSet), fade_flashes, move_sparks));
+    }
+}
+
+#[derive(Component)]
+struct Flash(Timer);
+
+#[derive(Component)]
+struct Spark {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+// Atomic captures blow up the captor and every piece but pawns on the
+// squares around the capture. Losing a king this way, or taking it
+// outright, loses the game.
+pub fn explode(
+    mut commands: Commands,
+    mut captures: EventReader<PieceCaptured>,
+    mut variant: ResMut<ActiveVariant>,
+    mut zones: ResMut<CaptureZones>,
+    placement: Res<Placement>,
+    mut piece_query: Query<(Entity, &mut Transform, &PieceType, Has<WhitePiece>), With<Movable>>,
+) {
+    for capture in captures.read() {
+        if variant.rules.capture_effect() != CaptureEffect::Explode {
+            continue;
+        }
+        if capture.piece == PieceType::King && variant.result.is_none() {
+            variant.result = Some(Outcome::Win(capture.side.opponent()));
+        }
+        let (col, row) = capture.square;
+        spawn_explosion(&mut commands, placement.square_center(capture.square));
+
+        for (piece, mut transform, piece_type, is_white) in piece_query.iter_mut() {
+            let Some((piece_col, piece_row)) = placement.square_at(transform.translation.truncate()) else {
+                continue;
+            };
+            let nearby = (piece_col - col).abs() <= 1 && (piece_row - row).abs() <= 1;
+            if piece != capture.captor && !(nearby && *piece_type != PieceType::Pawn) {
+                continue;
+            }
+
+            zones.capture(&mut transform, is_white);
+            commands.entity(piece).remove::<Movable>();
+            if *piece_type == PieceType::King && variant.result.is_none() {
+                let winner = if is_white { Side::Black } else { Side::White };
+                variant.result = Some(Outcome::Win(winner));
+            }
+        }
+    }
+}
+
+fn spawn_explosion(commands: &mut Commands, center: Vec2) {
+    commands.spawn((
+        Sprite::from_color(Color::srgba(1.0, 0.8, 0.3, 0.9), Vec2::splat(TILE_SIZE * 3.0)),
+        Transform::from_xyz(center.x, center.y, 3.0),
+        Flash(Timer::from_seconds(FLASH_SECONDS, TimerMode::Once)),
+    ));
+
+    for i in 0..SPARKS {
+        let angle = i as f32 / SPARKS as f32 * TAU;
+        commands.spawn((
+            Sprite::from_color(Color::srgb(1.0, 0.5, 0.1), Vec2::splat(SPARK_SIZE)),
+            Transform::from_xyz(center.x, center.y, 3.0),
+            Spark {
+                velocity: Vec2::from_angle(angle) * SPARK_SPEED,
+                timer: Timer::from_seconds(SPARK_SECONDS, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn fade_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flash_query: Query<(Entity, &mut Sprite, &mut Flash)>,
+) {
+    for (entity, mut sprite, mut flash) in flash_query.iter_mut() {
+        flash.0.tick(time.delta());
+        sprite.color.set_alpha(0.9 * (1.0 - flash.0.fraction()));
+        if flash.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn move_sparks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut spark_query: Query<(Entity, &mut Transform, &mut Sprite, &mut Spark)>,
+) {
+    for (entity, mut transform, mut sprite, mut spark) in spark_query.iter_mut() {
+        spark.timer.tick(time.delta());
+        transform.translation += (spark.velocity * time.delta_secs()).extend(0.0);
+        sprite.color.set_alpha(1.0 - spark.timer.fraction());
+        if spark.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
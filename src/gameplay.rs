@@ -1,36 +1,92 @@
 use bevy::prelude::*; 
 use bevy::math::bounding::{Aabb2d, IntersectsVolume}; 
 use super::cursor::*;
+use super::editor::not_editing;
 use super::pieces::*;
 use super::position::*;
+use super::rules::*;
+use super::variants::{game_running, ActiveVariant};
 
-const PIECESIZE: f32 = 37.5;
-const MOVEOVER: f32 = 40.0; 
-const RESET_LIMIT: f32 = -450.0; 
-const SCALER: f32 = 0.40; 
+pub const PIECESIZE: f32 = 37.5;
 
 pub struct GameplayPlugin; 
 impl Plugin for GameplayPlugin {
     fn build (&self, app: &mut App) {
         app
             .insert_resource( Previous {position: Vec2::new(0.0, 0.0)} )
-            .add_systems(Update, (grab.after(update_cursor_pos), drag).chain())
-            .add_systems(Update, (drop, (illegal_black_mv, illegal_white_mv)).chain())
-            .add_systems(Update, (take_white, take_black));
+            .add_systems(Update, (grab.after(update_cursor_pos), drag).chain().run_if(not_editing.and(game_running)))
+            .add_event::<MoveMade>()
+            .add_event::<PieceCaptured>()
+            .add_event::<Castled>()
+            .add_systems(
+                Update,
+                (
+                    drop,
+                    castle,
+                    variant_move,
+                    (illegal_black_mv, illegal_white_mv),
+                    (take_white, take_black),
+                    mark_moved,
+                    announce_move,
+                )
+                    .chain()
+                    .in_set(MoveSet)
+                    .run_if(not_editing),
+            );
     }
 }
 
+// Set holding the systems that settle a dropped piece, captures included.
+#[derive(SystemSet, Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct MoveSet;
+
+// Sent once a dropped piece has settled on a new square.
+#[derive(Event)]
+pub struct MoveMade {
+    pub side: Side,
+    // None when the piece was dropped in from a reserve.
+    pub from: Option<Square>,
+    pub to: Square,
+}
+
 #[derive(Resource)]
 struct Previous{
     position: Vec2,
 }
 
 #[derive(Component)]
-struct Draggable; 
+pub struct Draggable; 
 
 #[derive(Component)]
 struct Dropped;
 
+// Queried pieces with what castling needs to know about them.
+type CastlingPiece = (Entity, &'static mut Transform, &'static PieceType, Has<WhitePiece>, Has<Unmoved>, Has<Dropped>);
+// Queried pieces with what the variant rules need to know about them.
+type VariantPiece = (Entity, &'static mut Transform, &'static PieceType, Has<WhitePiece>, Has<Reserve>);
+// Pieces that can be taken by the one just dropped.
+type TakenPiece = (Entity, &'static mut Transform, &'static PieceType, Has<Promoted>);
+// Pieces of one color still standing on their squares.
+type Waiting<Color> = (With<Color>, Without<Dropped>);
+// The piece of one color that was just dropped.
+type JustDropped<Color> = (With<Dropped>, With<Color>, With<Movable>);
+// A piece just dropped that had not moved before.
+type FirstMove = (Added<Dropped>, With<Unmoved>);
+
+// Sent when a king castles, alongside the usual MoveMade.
+#[derive(Event)]
+pub struct Castled;
+
+// Sent when a dropped piece takes an enemy piece on the given square.
+#[derive(Event)]
+pub struct PieceCaptured {
+    pub captor: Entity,
+    pub square: Square,
+    // The piece that was taken, already moved off the board.
+    pub piece: PieceType,
+    pub side: Side,
+}
+
 fn drag(
     cursor_pos: Res<MouseWorldCoords>, 
     piece: Single<&mut Transform, With<Draggable>>
@@ -43,6 +99,8 @@ fn drag(
     }
 }
 
+// Picks up the piece under the cursor. Pieces can overlap, e.g. in the
+// Crazyhouse reserves, so only the one closest to the cursor is taken.
 fn grab(
     mut commands: Commands,
     cursor_pos: Res<MouseWorldCoords>, 
@@ -51,23 +109,32 @@ fn grab(
     mouse: Res<ButtonInput<MouseButton>>,
     mut previous: ResMut<Previous>,
 ) {
-    for (piece, transform) in piece_query.iter() {
-        let x_max = transform.translation.x + PIECESIZE; let y_max = transform.translation.y + PIECESIZE;
-        let x_min = transform.translation.x - PIECESIZE; let y_min = transform.translation.y - PIECESIZE;
-        
-        if let Some(pos) = cursor_pos.0 {
-            if (pos.x >= x_min && pos.x <= x_max) && (pos.y >= y_min && pos.y <= y_max) {
-                if mouse.just_pressed(MouseButton::Left) {
-                    for previous in dropped_query.iter() {
-                         commands.entity(previous).remove::<Dropped>();
-                    }
-                    previous.position.x = transform.translation.x; 
-                    previous.position.y = transform.translation.y; 
-                    commands.entity(piece).insert(Draggable); 
-                }
-            }
-        }
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
     }
+    let Some(pos) = cursor_pos.0 else {
+        return;
+    };
+
+    let closest = piece_query
+        .iter()
+        .filter(|(_, transform)| {
+            (pos.x - transform.translation.x).abs() <= PIECESIZE
+                && (pos.y - transform.translation.y).abs() <= PIECESIZE
+        })
+        .min_by(|(_, a), (_, b)| {
+            let distance = |transform: &Transform| transform.translation.truncate().distance_squared(pos);
+            distance(a).total_cmp(&distance(b))
+        });
+    let Some((piece, transform)) = closest else {
+        return;
+    };
+
+    for previous in dropped_query.iter() {
+        commands.entity(previous).remove::<Dropped>();
+    }
+    previous.position = transform.translation.truncate();
+    commands.entity(piece).insert(Draggable);
 }
 
 fn drop(
@@ -83,14 +150,184 @@ fn drop(
     }
 }
 
+// Dropping an unmoved king onto one of its own unmoved rooks castles, which
+// covers Chess960 setups as well. The king ends on the g or c file with the
+// rook beside it on the f or d file. Illegal attempts are left for the
+// friendly collision check to undo.
+fn castle(
+    mut commands: Commands,
+    previous: Res<Previous>,
+    placement: Res<Placement>,
+    mut piece_query: Query<CastlingPiece, With<Movable>>,
+    mut castled: EventWriter<Castled>,
+) {
+    let mut dropped_king = None;
+    for (piece, transform, piece_type, is_white, unmoved, dropped) in piece_query.iter() {
+        if dropped && unmoved && *piece_type == PieceType::King {
+            dropped_king = Some((piece, transform.translation.truncate(), is_white));
+        }
+    }
+    let Some((king, king_pos, is_white)) = dropped_king else {
+        return;
+    };
+    let from = placement.square_at(previous.position);
+    let target = placement.square_at(king_pos);
+    let (Some(from), Some(target)) = (from, target) else {
+        return;
+    };
+    let side = if is_white { Side::White } else { Side::Black };
+
+    // Everything but the king and the rook it castles with.
+    let mut rook = None;
+    let mut others = Vec::new();
+    for (piece, transform, piece_type, white, unmoved, _) in piece_query.iter() {
+        if piece == king {
+            continue;
+        }
+        let own_rook = white == is_white && unmoved && *piece_type == PieceType::Rook;
+        if own_rook && placement.square_at(transform.translation.truncate()) == Some(target) {
+            rook = Some(piece);
+        }
+        else {
+            others.push((transform, piece_type, white));
+        }
+    }
+    let board = BoardState::collect(&placement, others.into_iter());
+    let Some(rook) = rook else {
+        return;
+    };
+    let row = board.home_row(side);
+    if from.1 != row || target.1 != row {
+        return;
+    }
+
+    let (king_to, rook_to) = if target.0 > from.0 {
+        (board.width - 2, board.width - 3)
+    }
+    else {
+        (2, 3)
+    };
+    let lowest = from.0.min(target.0).min(king_to).min(rook_to);
+    let highest = from.0.max(target.0).max(king_to).max(rook_to);
+    let path_clear = (lowest..=highest).all(|col| board.get((col, row)).is_none());
+    let king_safe = (from.0.min(king_to)..=from.0.max(king_to))
+        .all(|col| !board.is_attacked((col, row), side.opponent()));
+    if !path_clear || !king_safe {
+        return;
+    }
+
+    for (piece, mut transform, _, _, _, _) in piece_query.iter_mut() {
+        let col = if piece == king { king_to } else if piece == rook { rook_to } else { continue };
+        let center = placement.square_center((col, row));
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        commands.entity(piece).remove::<Unmoved>();
+    }
+    castled.write(Castled);
+}
+
+// Lets the active variant turn down a move before it is settled. Pieces
+// dropped from a reserve must land on an empty square the variant allows.
+fn variant_move(
+    mut commands: Commands,
+    previous: Res<Previous>,
+    placement: Res<Placement>,
+    variant: Res<ActiveVariant>,
+    just_dropped: Query<Entity, Added<Dropped>>,
+    mut piece_query: Query<VariantPiece, With<Movable>>,
+) {
+    let mut moved = None;
+    let mut others = Vec::new();
+    for (piece, transform, piece_type, is_white, reserve) in piece_query.iter() {
+        if just_dropped.contains(piece) {
+            moved = Some((piece, transform.translation.truncate(), *piece_type, is_white, reserve));
+        }
+        else if !reserve {
+            others.push((transform, piece_type, is_white));
+        }
+    }
+    let Some((piece, pos, piece_type, is_white, reserve)) = moved else {
+        return;
+    };
+    let from = placement.square_at(previous.position);
+    let to = placement.square_at(pos);
+    if from == to && !reserve {
+        return;
+    }
+
+    let mut board = BoardState::collect(&placement, others.into_iter());
+    let side = if is_white { Side::White } else { Side::Black };
+    let allowed = match (from, to) {
+        (_, Some(to)) if reserve => {
+            board.get(to).is_none() && variant.rules.allows_drop(&board, side, piece_type, to)
+        }
+        (Some(from), Some(to)) => {
+            board.pieces.insert(from, (side, piece_type));
+            variant.rules.allows_move(&board, from, to)
+        }
+        _ => !reserve,
+    };
+
+    let Ok((_, mut transform, _, _, _)) = piece_query.get_mut(piece) else {
+        return;
+    };
+    if !allowed {
+        transform.translation.x = previous.position.x;
+        transform.translation.y = previous.position.y;
+    }
+    else if let (true, Some(to)) = (reserve, to) {
+        let center = placement.square_center(to);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        transform.scale = Vec3::ONE;
+        commands.entity(piece).remove::<Reserve>();
+    }
+}
+
+// Kings and rooks lose their castling rights once they leave their square. 
+fn mark_moved(
+    mut commands: Commands,
+    previous: Res<Previous>,
+    placement: Res<Placement>,
+    moved_query: Query<(Entity, &Transform), FirstMove>,
+) {
+    for (piece, transform) in moved_query.iter() {
+        let to = placement.square_at(transform.translation.truncate());
+        if to != placement.square_at(previous.position) {
+            commands.entity(piece).remove::<Unmoved>();
+        }
+    }
+}
+
+fn announce_move(
+    previous: Res<Previous>,
+    placement: Res<Placement>,
+    moved_query: Query<(&Transform, Has<WhitePiece>), Added<Dropped>>,
+    mut moves: EventWriter<MoveMade>,
+) {
+    for (transform, is_white) in moved_query.iter() {
+        let from = placement.square_at(previous.position);
+        let to = placement.square_at(transform.translation.truncate());
+        if let Some(to) = to
+            && from != Some(to)
+        {
+            let side = if is_white { Side::White } else { Side::Black };
+            moves.write(MoveMade { side, from, to });
+        }
+    }
+}
+
 fn take_white(
     mut commands: Commands,
     mut removal: ResMut<CaptureZones>,
-    taken_query: Query<(Entity, &mut Transform),  (With<WhitePiece>, Without<Dropped>)>,
-    captor_query: Single<&Transform, (With<Dropped>, With<BlackPiece>)>,  
+    placement: Res<Placement>,
+    variant: Res<ActiveVariant>,
+    taken_query: Query<TakenPiece, Waiting<WhitePiece>>,
+    captor_query: Single<(Entity, &Transform), JustDropped<BlackPiece>>,
+    mut captures: EventWriter<PieceCaptured>,
 ) {
-    let capture = captor_query.into_inner(); 
-    for (piece, mut taken) in taken_query.into_iter() {
+    let (captor, capture) = captor_query.into_inner(); 
+    for (piece, mut taken, piece_type, promoted) in taken_query.into_iter() {
         let collision = check_for_collisions(
             Aabb2d::new(
                 taken.translation.truncate(),
@@ -102,14 +339,18 @@ fn take_white(
             )
         ); 
         if collision {
-            if removal.white_pos.x > RESET_LIMIT {
-                removal.white_pos.y -= MOVEOVER; 
-                removal.white_pos.x = CAPTURE_START;
+            if let Some(square) = placement.square_at(capture.translation.truncate()) {
+                captures.write(PieceCaptured { captor, square, piece: *piece_type, side: Side::White });
+            }
+            removal.capture(&mut taken, true);
+            match variant.rules.capture_effect() {
+                CaptureEffect::Remove | CaptureEffect::Explode => {
+                    commands.entity(piece).remove::<Movable>();
+                }
+                CaptureEffect::Reserve => to_reserve(
+                    &mut commands, piece, *piece_type, promoted, Side::Black,
+                ),
             }
-            taken.translation.x = removal.white_pos.x; taken.translation.y = removal.white_pos.y; 
-            taken.scale.x = SCALER; taken.scale.y = SCALER; 
-            removal.white_pos.x += MOVEOVER;
-            commands.entity(piece).remove::<Movable>(); 
         }
     }
 }
@@ -117,11 +358,14 @@ fn take_white(
 fn take_black(
     mut commands: Commands,
     mut removal: ResMut<CaptureZones>,
-    taken_query: Query<(Entity, &mut Transform), (With<BlackPiece>, Without<Dropped>)>,
-    captor_query: Single<&Transform, (With<Dropped>, With<WhitePiece>)>, 
+    placement: Res<Placement>,
+    variant: Res<ActiveVariant>,
+    taken_query: Query<TakenPiece, Waiting<BlackPiece>>,
+    captor_query: Single<(Entity, &Transform), JustDropped<WhitePiece>>,
+    mut captures: EventWriter<PieceCaptured>,
 ) {
-    let capture = captor_query.into_inner(); 
-    for (piece, mut taken) in taken_query.into_iter() {
+    let (captor, capture) = captor_query.into_inner(); 
+    for (piece, mut taken, piece_type, promoted) in taken_query.into_iter() {
         let collision = check_for_collisions(
             Aabb2d::new(    
                 taken.translation.truncate(),
@@ -134,22 +378,50 @@ fn take_black(
         ); 
 
         if collision {
-            if removal.black_pos.x > RESET_LIMIT {
-                removal.black_pos.y -= MOVEOVER; 
-                removal.black_pos.x = CAPTURE_START;
+            if let Some(square) = placement.square_at(capture.translation.truncate()) {
+                captures.write(PieceCaptured { captor, square, piece: *piece_type, side: Side::Black });
+            }
+            removal.capture(&mut taken, false);
+            match variant.rules.capture_effect() {
+                CaptureEffect::Remove | CaptureEffect::Explode => {
+                    commands.entity(piece).remove::<Movable>();
+                }
+                CaptureEffect::Reserve => to_reserve(
+                    &mut commands, piece, *piece_type, promoted, Side::White,
+                ),
             }
-            taken.translation.x = removal.black_pos.x; taken.translation.y = removal.black_pos.y; 
-            taken.scale.x = SCALER; taken.scale.y = SCALER; 
-            removal.black_pos.x += MOVEOVER;
-            commands.entity(piece).remove::<Movable>(); 
         }
     }
 }
 
+// The captured piece changes sides and waits in the captor's reserve, ready
+// to be dropped back onto the board. Promoted pieces go back to being pawns.
+fn to_reserve(
+    commands: &mut Commands,
+    piece: Entity,
+    piece_type: PieceType,
+    promoted: bool,
+    captor: Side,
+) {
+    let piece_type = if promoted { PieceType::Pawn } else { piece_type };
+
+    let mut entity = commands.entity(piece);
+    entity
+        .remove::<(WhitePiece, BlackPiece, Promoted, Unmoved)>()
+        .insert((piece_type, Reserve));
+    match captor {
+        Side::White => entity.insert(WhitePiece),
+        Side::Black => entity.insert(BlackPiece),
+    };
+    if piece_type == PieceType::Pawn {
+        entity.insert(Pawn);
+    }
+}
+
 fn illegal_black_mv( 
     previous: Res<Previous>, 
-    friendly_query: Query<&Transform, (With<BlackPiece>, Without<Dropped>)>, 
-    captor_query: Single<&mut Transform, (With<BlackPiece>, With<Dropped>)>, 
+    friendly_query: Query<&Transform, Waiting<BlackPiece>>, 
+    captor_query: Single<&mut Transform, JustDropped<BlackPiece>>, 
 ) {
     let mut captor_transform = captor_query.into_inner(); 
     for friendly_transfrom in friendly_query.into_iter() {
@@ -169,8 +441,8 @@ fn illegal_black_mv(
 
 fn illegal_white_mv(
     previous: Res<Previous>, 
-    friendly_query: Query<&Transform, (With<WhitePiece>, Without<Dropped>)>, 
-    captor_query: Single<&mut Transform, (With<WhitePiece>, With<Dropped>)>, 
+    friendly_query: Query<&Transform, Waiting<WhitePiece>>, 
+    captor_query: Single<&mut Transform, JustDropped<WhitePiece>>, 
 ) {
     let mut captor_transform = captor_query.into_inner(); 
     for friendly_transfrom in friendly_query.into_iter() {
@@ -192,10 +464,32 @@ fn check_for_collisions(
     taken: Aabb2d,
     captor: Aabb2d,
 ) -> bool {
-    if captor.intersects(&taken) {
-        true
-    }
-    else {
-        false
+    captor.intersects(&taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_pieces_are_grabbed_one_at_a_time() {
+        let mut app = App::new();
+        let mut mouse = ButtonInput::<MouseButton>::default();
+        mouse.press(MouseButton::Left);
+        app
+            .insert_resource(mouse)
+            .insert_resource(MouseWorldCoords(Some(Vec2::new(10.0, 0.0))))
+            .insert_resource(Previous { position: Vec2::ZERO })
+            .add_systems(Update, grab);
+
+        // Reserve pieces stacked less than a piece apart.
+        let far = app.world_mut().spawn((Transform::from_xyz(-20.0, 0.0, 1.0), Movable)).id();
+        let near = app.world_mut().spawn((Transform::from_xyz(20.0, 0.0, 1.0), Movable)).id();
+        app.update();
+
+        let world = app.world_mut();
+        let grabbed: Vec<Entity> = world.query_filtered::<Entity, With<Draggable>>().iter(world).collect();
+        assert_eq!(grabbed, vec![near]);
+        assert!(!world.entity(far).contains::<Draggable>());
     }
-}
\ No newline at end of file
+}
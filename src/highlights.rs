@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use super::gameplay::MoveMade;
+use super::pieces::*;
+use super::position::*;
+use super::reset::NewGame;
+use super::rules::*;
+use super::theme::Theme;
+
+pub struct HighlightsPlugin;
+impl Plugin for HighlightsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LastMove>()
+            .add_systems(Update, (track_moves, highlight_tiles).chain());
+    }
+}
+
+// Squares of the last move and of any king left in check by it.
+#[derive(Resource, Default)]
+pub struct LastMove {
+    pub from: Option<Square>,
+    pub to: Option<Square>,
+    pub checked: Vec<Square>,
+}
+
+pub fn track_moves(
+    mut moves: EventReader<MoveMade>,
+    mut new_game: EventReader<NewGame>,
+    mut last_move: ResMut<LastMove>,
+    placement: Res<Placement>,
+    piece_query: Query<(&Transform, &PieceType, Has<WhitePiece>), With<Movable>>,
+) {
+    if new_game.read().count() > 0 {
+        *last_move = LastMove::default();
+    }
+    let Some(made) = moves.read().last() else {
+        return;
+    };
+
+    let board = BoardState::collect(&placement, piece_query.iter());
+    *last_move = LastMove {
+        from: made.from,
+        to: Some(made.to),
+        checked: [Side::White, Side::Black]
+            .into_iter()
+            .filter(|side| board.in_check(*side))
+            .filter_map(|side| board.king(side))
+            .collect(),
+    };
+}
+
+// Tints the tiles on the tilemap; untouched tiles go back to plain white.
+fn highlight_tiles(
+    last_move: Res<LastMove>,
+    theme: Res<Theme>,
+    mut tile_query: Query<(&TilePos, &mut TileColor)>,
+) {
+    if !last_move.is_changed() && !theme.is_changed() {
+        return;
+    }
+    for (tile_pos, mut color) in tile_query.iter_mut() {
+        let square = (tile_pos.x as i32, tile_pos.y as i32);
+        color.0 = if last_move.checked.contains(&square) {
+            theme.check_color()
+        }
+        else if last_move.from == Some(square) || last_move.to == Some(square) {
+            theme.last_move_color()
+        }
+        else {
+            Color::WHITE
+        };
+    }
+}
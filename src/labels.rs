@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use super::orientation::Orientation;
+use super::position::*;
+
+const LABEL_OFFSET: f32 = 30.0;
+const LABEL_SIZE: f32 = 24.0;
+
+pub struct LabelsPlugin;
+impl Plugin for LabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, spawn_labels.after(setup_placement))
+            .add_systems(Update, place_labels);
+    }
+}
+
+// File letter or rank number drawn beside the board.
+#[derive(Component)]
+enum Coordinate {
+    File(i32),
+    Rank(i32),
+}
+
+fn spawn_labels(mut commands: Commands, placement: Res<Placement>) {
+    let files = (0..placement.width).map(|col| (file_name(col).to_string(), Coordinate::File(col)));
+    let ranks = (0..placement.height).map(|row| ((row + 1).to_string(), Coordinate::Rank(row)));
+
+    for (name, coordinate) in files.chain(ranks) {
+        commands.spawn((
+            Text2d::new(name),
+            TextFont {
+                font_size: LABEL_SIZE,
+                ..default()
+            },
+            Transform::from_xyz(0.0, 0.0, 1.0),
+            coordinate,
+        ));
+    }
+}
+
+// Files run along the bottom edge and ranks up the left edge as the board is
+// seen, so on a flipped board they move to the opposite edges.
+fn place_labels(
+    orientation: Res<Orientation>,
+    placement: Res<Placement>,
+    mut label_query: Query<(&mut Transform, Ref<Coordinate>)>,
+) {
+    let side = if orientation.flipped { -1.0 } else { 1.0 };
+    for (mut transform, coordinate) in label_query.iter_mut() {
+        if !orientation.is_changed() && !coordinate.is_added() {
+            continue;
+        }
+        let position = match *coordinate {
+            Coordinate::File(col) => Vec2::new(
+                placement.square_center((col, 0)).x,
+                side * (placement.bottom_edge() - LABEL_OFFSET),
+            ),
+            Coordinate::Rank(row) => Vec2::new(
+                side * (placement.left_edge() - LABEL_OFFSET),
+                placement.square_center((0, row)).y,
+            ),
+        };
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
@@ -10,11 +10,32 @@ mod pieces; use pieces::PiecesPlugin;
 mod position; use position::PositionPlugin;
 mod gameplay; use gameplay::GameplayPlugin; 
 mod reset; use reset::ResetPlugin; 
+mod rules;
+mod editor; use editor::EditorPlugin;
+mod chess960; use chess960::Chess960Plugin;
+mod variants; use variants::VariantsPlugin;
+mod explosions; use explosions::ExplosionsPlugin;
+mod orientation; use orientation::OrientationPlugin;
+mod labels; use labels::LabelsPlugin;
+mod highlights; use highlights::HighlightsPlugin;
+mod annotations; use annotations::AnnotationsPlugin;
+mod animation; use animation::AnimationPlugin;
+mod audio; use audio::SoundPlugin;
+mod theme; use theme::{ThemePlugin, UserThemeSourcePlugin};
+mod atlas; use atlas::AtlasPlugin;
+mod view; use view::ViewPlugin;
+mod settings; use settings::SettingsPlugin;
+mod controls; use controls::{Action, Bindings};
 
 fn main() {
     App::new()
         .add_plugins((
-            DefaultPlugins,
+            UserThemeSourcePlugin,
+            // Watch asset files so edited images and themes show up live.
+            DefaultPlugins.set(AssetPlugin {
+                watch_for_changes_override: Some(true),
+                ..default()
+            }),
             WindowsPlugin,
             BoardPlugin,
             PositionPlugin,
@@ -22,12 +43,22 @@ fn main() {
             PiecesPlugin,
             GameplayPlugin,
             ResetPlugin, 
+            EditorPlugin,
+            Chess960Plugin,
+            VariantsPlugin,
+            ExplosionsPlugin,
         ))
+        .add_plugins((OrientationPlugin, LabelsPlugin, HighlightsPlugin, AnnotationsPlugin, AnimationPlugin))
+        .add_plugins((SoundPlugin, ThemePlugin, AtlasPlugin, ViewPlugin, SettingsPlugin))
         .add_plugins(TilemapPlugin)
         .add_systems(Startup, spawn_text)
+        .add_systems(Update, update_help)
         .run();
 }
 
+#[derive(Component)]
+struct HelpText;
+
 fn spawn_text(mut commands: Commands) {
     commands
         .spawn(Node {
@@ -36,10 +67,44 @@ fn spawn_text(mut commands: Commands) {
             left: Val::Px(12.0),
             ..default()
         })
-        .with_child(Text::new(concat!(
-            "Press 4 to which to windowed, and 5 to switch to fullscreen\n",
-            "Press 1, 2, 3 to change resolution sizes\n",
-            "Press esc to reset the board\n",
-            "Press Q(ueen), R(ook), B(ishop), K(night) to promote pawns."
-        )));
+        .with_child((Text::new(""), HelpText));
+}
+
+// Rewrites the help whenever a key is rebound, so it always names the current keys.
+fn update_help(
+    bindings: Res<Bindings>,
+    mut help_query: Query<&mut Text, With<HelpText>>,
+) {
+    if !bindings.is_changed() {
+        return;
+    }
+    let key = |action| bindings.name(action);
+    let help = [
+        format!("Press {} to switch to windowed, and {} to switch to fullscreen", key(Action::Windowed), key(Action::Fullscreen)),
+        format!("Press {}, {}, {} to change resolution sizes", key(Action::SmallWindow), key(Action::MediumWindow), key(Action::LargeWindow)),
+        format!("Press {} to reset the board", key(Action::ResetBoard)),
+        format!(
+            "Press {} (queen), {} (rook), {} (bishop), {} (knight) to promote pawns.",
+            key(Action::PromoteQueen), key(Action::PromoteRook), key(Action::PromoteBishop), key(Action::PromoteKnight),
+        ),
+        format!("Press {} to edit the position, {} to play from it.", key(Action::EditPosition), key(Action::PlayFromEditor)),
+        format!(
+            "Press {} for a new Chess960 game, {} for a standard one, {} to log the FEN.",
+            key(Action::NewChess960), key(Action::StandardGame), key(Action::LogFen),
+        ),
+        "Castle by dropping the king onto its rook.".to_string(),
+        format!("Press {} to switch variant. In Crazyhouse, drag captured pieces back onto the board.", key(Action::SwitchVariant)),
+        format!(
+            "Press {} to flip the board, {} to flip to the side to move after every move.",
+            key(Action::FlipBoard), key(Action::AutoFlip),
+        ),
+        "Right-drag to draw arrows, right-click to circle squares. Hold Shift, Alt or Ctrl for other colours.".to_string(),
+        format!("Press {} to mute, {} and {} to change the volume.", key(Action::Mute), key(Action::VolumeDown), key(Action::VolumeUp)),
+        format!("Press {} to switch theme.", key(Action::SwitchTheme)),
+        format!("Scroll to zoom, drag with the middle button to pan, press {} to reset the view.", key(Action::ResetView)),
+        format!("Press {} for settings and to change keys.", key(Action::Settings)),
+    ];
+    for mut text in help_query.iter_mut() {
+        text.0 = help.join("\n");
+    }
 }
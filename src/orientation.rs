@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+use super::board::MainCamera;
+use super::editor::Editor;
+use super::controls::*;
+use super::gameplay::MoveMade;
+use super::pieces::*;
+use super::reset::NewGame;
+
+pub struct OrientationPlugin;
+impl Plugin for OrientationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Orientation>()
+            .add_systems(Update, (flip_keys, follow_turns, rotate_camera, keep_upright).chain());
+    }
+}
+
+// Which way up the board is shown. Flipping turns the camera half way round,
+// so the tilemap, pieces and capture zones all swap sides together.
+#[derive(Resource, Default)]
+pub struct Orientation {
+    pub flipped: bool,
+    // In hot-seat games, always show the side to move at the bottom.
+    pub auto_flip: bool,
+    pub side_to_move: Side,
+}
+
+impl Orientation {
+    pub fn rotation(&self) -> Quat {
+        if self.flipped { Quat::from_rotation_z(PI) } else { Quat::IDENTITY }
+    }
+
+    fn face_side_to_move(&mut self) {
+        self.flipped = self.side_to_move == Side::Black;
+    }
+}
+
+fn flip_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut orientation: ResMut<Orientation>,
+) {
+    if bindings.just_pressed(&keys, Action::FlipBoard) {
+        orientation.flipped = !orientation.flipped;
+    }
+    if bindings.just_pressed(&keys, Action::AutoFlip) {
+        orientation.auto_flip = !orientation.auto_flip;
+        if orientation.auto_flip {
+            orientation.face_side_to_move();
+        }
+    }
+}
+
+// There are no enforced turns, so the side to move is whoever did not move
+// last. A position from the editor starts with the side picked there.
+fn follow_turns(
+    mut moves: EventReader<MoveMade>,
+    mut new_game: EventReader<NewGame>,
+    editor: Res<Editor>,
+    mut orientation: ResMut<Orientation>,
+) {
+    let mut changed = false;
+    if new_game.read().count() > 0 {
+        orientation.side_to_move = Side::White;
+        changed = true;
+    }
+    // The editor only changes while open, so a change seen closed is play starting.
+    if editor.is_changed() && !editor.is_added() && !editor.active {
+        orientation.side_to_move = editor.side_to_move;
+        changed = true;
+    }
+    for made in moves.read() {
+        orientation.side_to_move = made.side.opponent();
+        changed = true;
+    }
+    if changed && orientation.auto_flip {
+        orientation.face_side_to_move();
+    }
+}
+
+fn rotate_camera(
+    orientation: Res<Orientation>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !orientation.is_changed() {
+        return;
+    }
+    for mut transform in camera_query.iter_mut() {
+        transform.rotation = orientation.rotation();
+    }
+}
+
+// Sprites and world text, everything drawn in the world but the camera.
+type Upright = (Or<(With<Sprite>, With<Text2d>)>, Without<MainCamera>);
+
+// Counter-rotates sprites and world text so they stay upright on a flipped board.
+fn keep_upright(
+    orientation: Res<Orientation>,
+    mut upright_query: Query<&mut Transform, Upright>,
+) {
+    let rotation = orientation.rotation();
+    for mut transform in upright_query.iter_mut() {
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
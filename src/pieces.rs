@@ -1,39 +1,42 @@
+use super::controls::*;
 use super::position::*;
+use super::variants::ActiveVariant;
 use bevy::prelude::*;
 
-// Edit piece png(s) here
-
-// Black Pieces PNG
-const BLACK_PAWN_IMAGE: &str = "dandelion.png";
-const BLACK_ROOK_IMAGE: &str = "black_rook.png";
-const BLACK_KNIGHT_IMAGE: &str = "clove.png";
-const BLACK_BISHOP_IMAGE: &str = "black_bishop.png";
-const BLACK_QUEEN_IMAGE: &str = "black_queen.png";
-const BLACK_KING_IMAGE: &str = "black_king.png";
-// White Pieces PNG
-const WHITE_PAWN_IMAGE: &str = "caterpillar.png";
-const WHITE_ROOK_IMAGE: &str = "white_rook.png";
-const WHITE_KNIGHT_IMAGE: &str = "white_knight.png";
-const WHITE_BISHOP_IMAGE: &str = "ladybug.png";
-const WHITE_QUEEN_IMAGE: &str = "white_queen.png";
-const WHITE_KING_IMAGE: &str = "white_king.png";
-
 pub struct PiecesPlugin;
 impl Plugin for PiecesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_pieces.after(setup_placement))
+        app.init_resource::<BackRank>()
+            .add_systems(Startup, spawn_pieces.after(setup_placement))
             .add_systems(Update, promote_black)
             .add_systems(Update, promote_white);
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum PieceType {
     King,
     Queen,
     Bishop,
     Knight,
     Rook,
+    Pawn,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Side {
+    #[default]
+    White,
+    Black,
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::White => Side::Black,
+            Side::Black => Side::White,
+        }
+    }
 }
 
 #[derive(Component)]
@@ -48,150 +51,138 @@ pub struct Pawn;
 #[derive(Component)]
 pub struct Movable;
 
-fn spawn_pieces(mut commands: Commands, asset_server: Res<AssetServer>, board: Res<Placement>) {
-    spawn_white_pieces(&mut commands, &asset_server, &board);
-    spawn_pawns(&mut commands, &asset_server, &board);
-    spawn_black_pieces(&mut commands, &asset_server, &board);
+// Pawns that were promoted, which turn back into pawns when captured in Crazyhouse.
+#[derive(Component)]
+pub struct Promoted;
+
+// Captured pieces waiting beside the board to be dropped back in.
+#[derive(Component)]
+pub struct Reserve;
+
+// Pieces that have not left their starting square. Kings and rooks keep
+// their castling rights while they have it.
+#[derive(Component)]
+pub struct Unmoved;
+
+// Order of the pieces on each side's back rank, mirrored for both colors.
+#[derive(Resource, Clone, Copy)]
+pub struct BackRank {
+    pub pieces: [PieceType; 8],
+    // Chess960 starting position number, None for a standard game.
+    pub chess960: Option<u16>,
 }
 
-pub fn spawn_black_pieces(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    board: &Res<Placement>,
-) {
-    let names = vec![
-        BLACK_ROOK_IMAGE,
-        BLACK_KNIGHT_IMAGE,
-        BLACK_BISHOP_IMAGE,
-        BLACK_QUEEN_IMAGE,
-        BLACK_KING_IMAGE,
-        BLACK_BISHOP_IMAGE,
-        BLACK_KNIGHT_IMAGE,
-        BLACK_ROOK_IMAGE,
-    ];
-    let types = vec![
-        PieceType::Rook,
-        PieceType::Knight,
-        PieceType::Bishop,
-        PieceType::King,
-        PieceType::Queen,
-        PieceType::Bishop,
-        PieceType::Knight,
-        PieceType::Rook,
-    ];
-
-    for i in 1..=8 {
-        if let Some(&(x, y)) = board.positions.get(&format!("H{}", i)) {
-            commands.spawn((
-                Sprite::from_image(asset_server.load(names[i - 1])),
-                Transform::from_xyz(x, y, 1.0),
-                BlackPiece,
-                Pickable::default(),
-                Movable,
-            ));
+impl Default for BackRank {
+    fn default() -> Self {
+        BackRank {
+            pieces: [
+                PieceType::Rook,
+                PieceType::Knight,
+                PieceType::Bishop,
+                PieceType::Queen,
+                PieceType::King,
+                PieceType::Bishop,
+                PieceType::Knight,
+                PieceType::Rook,
+            ],
+            chess960: None,
         }
     }
 }
 
-pub fn spawn_white_pieces(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    board: &Res<Placement>,
+fn spawn_pieces(
+    mut commands: Commands,
+    board: Res<Placement>,
+    back_rank: Res<BackRank>,
+    variant: Res<ActiveVariant>,
 ) {
-    let names = vec![
-        WHITE_ROOK_IMAGE,
-        WHITE_KNIGHT_IMAGE,
-        WHITE_BISHOP_IMAGE,
-        WHITE_QUEEN_IMAGE,
-        WHITE_KING_IMAGE,
-        WHITE_BISHOP_IMAGE,
-        WHITE_KNIGHT_IMAGE,
-        WHITE_ROOK_IMAGE,
-    ];
-    let types = vec![
-        PieceType::Rook,
-        PieceType::Knight,
-        PieceType::Bishop,
-        PieceType::Queen,
-        PieceType::King,
-        PieceType::Bishop,
-        PieceType::Knight,
-        PieceType::Rook,
-    ];
-
-    for i in 1..=8 {
-        if let Some(&(x, y)) = board.positions.get(&format!("A{}", i)) {
-            commands.spawn((
-                Sprite::from_image(asset_server.load(names[i - 1])),
-                Transform::from_xyz(x, y, 1.0),
-                WhitePiece,
-                Pickable::default(),
-                Movable,
-            ));
+    let position = variant.rules.starting_position(&back_rank, &board);
+    spawn_position(&mut commands, &board, &position);
+}
+
+// Standard starting position for the given back rank, with a row of pawns in front.
+// On boards wider than eight the back rank is centred.
+pub fn standard_position(back_rank: &BackRank, placement: &Placement) -> Vec<(Square, Side, PieceType)> {
+    let last = placement.height - 1;
+    let offset = (placement.width - back_rank.pieces.len() as i32) / 2;
+    let mut position = Vec::new();
+    for col in 0..placement.width {
+        position.push(((col, 1), Side::White, PieceType::Pawn));
+        position.push(((col, last - 1), Side::Black, PieceType::Pawn));
+    }
+    for (col, piece) in back_rank.pieces.iter().enumerate() {
+        let col = col as i32 + offset;
+        if placement.contains((col, 0)) {
+            position.push(((col, 0), Side::White, *piece));
+            position.push(((col, last), Side::Black, *piece));
         }
     }
+    position
 }
 
-pub fn spawn_pawns(
+// Spawns every piece of a starting position on its square. 
+pub fn spawn_position(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    board: &Res<Placement>,
+    board: &Placement,
+    position: &[(Square, Side, PieceType)],
 ) {
-    // Spawning the White Pawns
-    for i in 1..=8 {
-        if let Some(&(x, y)) = board.positions.get(&format!("B{}", i)) {
-            commands.spawn((
-                Sprite::from_image(asset_server.load(WHITE_PAWN_IMAGE)),
-                Transform::from_xyz(x, y, 1.0),
-                WhitePiece,
-                Pawn,
-                Pickable::default(),
-                Movable,
-            ));
+    for &(square, side, piece) in position {
+        if let Some(&(x, y)) = board.positions.get(&square_name(square)) {
+            let entity = spawn_piece(commands, piece, side, Vec2::new(x, y));
+            commands.entity(entity).insert(Unmoved);
         }
     }
+}
 
-    // Spawning the Black Pawns
-    for i in 1..=8 {
-        if let Some(&(x, y)) = board.positions.get(&format!("G{}", i)) {
-            commands.spawn((
-                Sprite::from_image(asset_server.load(BLACK_PAWN_IMAGE)),
-                Transform::from_xyz(x, y, 1.0),
-                BlackPiece,
-                Pawn,
-                Pickable::default(),
-                Movable,
-            ));
-        }
+// Spawns a single movable piece, used when pieces are placed one at a time.
+// Its image is filled in from the piece atlas.
+pub fn spawn_piece(
+    commands: &mut Commands,
+    piece: PieceType,
+    side: Side,
+    position: Vec2,
+) -> Entity {
+    let mut entity = commands.spawn((
+        Sprite::default(),
+        Transform::from_xyz(position.x, position.y, 1.0),
+        piece,
+        Pickable::default(),
+        Movable,
+    ));
+    match side {
+        Side::White => entity.insert(WhitePiece),
+        Side::Black => entity.insert(BlackPiece),
+    };
+    if piece == PieceType::Pawn {
+        entity.insert(Pawn);
     }
+    entity.id()
 }
 
+// Pawns of one color, given by its marker component.
+type PawnsOf<Color> = (With<Pawn>, With<Color>);
+
 fn promote_black(
     mut commands: Commands,
-    mut pawn_query: Query<(Entity, &mut Sprite, &Transform), (With<Pawn>, With<BlackPiece>)>,
-    asset_server: Res<AssetServer>,
+    pawn_query: Query<(Entity, &Transform), PawnsOf<BlackPiece>>,
+    placement: Res<Placement>,
     keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
 ) {
-    for (entity, mut sprite, piece) in pawn_query.iter_mut() {
-        if (piece.translation.y <= -300.0 && piece.translation.y >= -400.0)
-            && (piece.translation.x <= 400.0 && piece.translation.x >= -400.0)
-        {
-            //*sprite = Sprite::from_image(asset_server.load("black_queen.png"));
-            if keys.just_released(KeyCode::KeyQ) {
-                *sprite = Sprite::from_image(asset_server.load(BLACK_QUEEN_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+    for (entity, piece) in pawn_query.iter() {
+        let square = placement.square_at(piece.translation.truncate());
+        if square.is_some_and(|(_, row)| row == 0) {
+            if bindings.just_released(&keys, Action::PromoteQueen) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Queen));
             }
-            if keys.just_released(KeyCode::KeyR) {
-                *sprite = Sprite::from_image(asset_server.load(BLACK_ROOK_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+            if bindings.just_released(&keys, Action::PromoteRook) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Rook));
             }
-            if keys.just_released(KeyCode::KeyK) {
-                *sprite = Sprite::from_image(asset_server.load(BLACK_KNIGHT_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+            if bindings.just_released(&keys, Action::PromoteKnight) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Knight));
             }
-            if keys.just_released(KeyCode::KeyB) {
-                *sprite = Sprite::from_image(asset_server.load(BLACK_BISHOP_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+            if bindings.just_released(&keys, Action::PromoteBishop) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Bishop));
             }
         }
     }
@@ -199,29 +190,25 @@ fn promote_black(
 
 fn promote_white(
     mut commands: Commands,
-    mut pawn_query: Query<(Entity, &mut Sprite, &Transform), (With<Pawn>, With<WhitePiece>)>,
-    asset_server: Res<AssetServer>,
+    pawn_query: Query<(Entity, &Transform), PawnsOf<WhitePiece>>,
+    placement: Res<Placement>,
     keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
 ) {
-    for (entity, mut sprite, piece) in pawn_query.iter_mut() {
-        if (piece.translation.y >= 300.0 && piece.translation.y <= 400.0)
-            && (piece.translation.x <= 400.0 && piece.translation.x >= -400.0)
-        {
-            if keys.just_released(KeyCode::KeyQ) {
-                *sprite = Sprite::from_image(asset_server.load(WHITE_QUEEN_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+    for (entity, piece) in pawn_query.iter() {
+        let square = placement.square_at(piece.translation.truncate());
+        if square.is_some_and(|(_, row)| row == placement.height - 1) {
+            if bindings.just_released(&keys, Action::PromoteQueen) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Queen));
             }
-            if keys.just_released(KeyCode::KeyB) {
-                *sprite = Sprite::from_image(asset_server.load(WHITE_BISHOP_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+            if bindings.just_released(&keys, Action::PromoteBishop) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Bishop));
             }
-            if keys.just_released(KeyCode::KeyK) {
-                *sprite = Sprite::from_image(asset_server.load(WHITE_KNIGHT_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+            if bindings.just_released(&keys, Action::PromoteKnight) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Knight));
             }
-            if keys.just_released(KeyCode::KeyR) {
-                *sprite = Sprite::from_image(asset_server.load(WHITE_ROOK_IMAGE));
-                commands.entity(entity).remove::<Pawn>();
+            if bindings.just_released(&keys, Action::PromoteRook) {
+                commands.entity(entity).remove::<Pawn>().insert((Promoted, PieceType::Rook));
             }
         }
     }
@@ -1,64 +1,155 @@
 use bevy::prelude::*;
 use std::collections::HashMap; 
+use super::atlas::PieceIcon;
+use super::board::BoardSize;
+use super::pieces::{PieceType, Side};
 
 type BoardPosition = String; 
 type Coordinates = (f32, f32); 
-pub const START_POS: f32 = -350.0;
-pub const CAPTURE_START: f32 = -600.0;
-const WHITE_CAPTURE_POS: f32 = 350.0;
-const BLACK_CAPTURE_POS: f32 = -250.0; 
-const WHITE_PLAYER_POS: f32 = -150.0; 
-const BLACK_PLAYER_POS: f32 = 450.0; 
+// Column and row of a square, counted from the bottom left corner.
+pub type Square = (i32, i32);
+pub const TILE_SIZE: f32 = 100.0;
+// Distance from the board's left edge to the capture zones and profiles.
+const CAPTURE_OFFSET: f32 = 200.0;
+// Heights of the capture zones and profiles, measured from the board's top
+// edge for black's side and from its bottom edge for white's.
+const WHITE_CAPTURE_POS: f32 = -50.0;
+const MOVEOVER: f32 = 40.0; 
+// How far a row of captured pieces reaches before starting the next one.
+const ROW_LENGTH: f32 = 150.0; 
+const SCALER: f32 = 0.40; 
+const BLACK_CAPTURE_POS: f32 = 150.0; 
+const WHITE_PLAYER_POS: f32 = 250.0; 
+const BLACK_PLAYER_POS: f32 = 50.0; 
 
 #[derive(Resource)]
 pub struct CaptureZones {
     pub white_pos: Vec2,
     pub black_pos: Vec2,
+    start_x: f32,
 }
 
-pub fn setup_profiles(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let w_coordinates = Vec2::new(CAPTURE_START, WHITE_CAPTURE_POS); 
-    let b_coordinates: Vec2 = Vec2::new(CAPTURE_START, BLACK_CAPTURE_POS); 
+impl CaptureZones {
+    // Shrinks a captured piece into the next free slot of its side's zone. 
+    pub fn capture(&mut self, taken: &mut Transform, is_white: bool) {
+        let removal = if is_white { &mut self.white_pos } else { &mut self.black_pos };
+        if removal.x > self.start_x + ROW_LENGTH {
+            removal.y -= MOVEOVER; 
+            removal.x = self.start_x;
+        }
+        taken.translation.x = removal.x; taken.translation.y = removal.y; 
+        taken.scale.x = SCALER; taken.scale.y = SCALER; 
+        removal.x += MOVEOVER;
+    }
+}
+
+pub fn setup_profiles(
+    mut commands: Commands,
+    placement: Res<Placement>,
+) {
+    let capture_start = placement.left_edge() - CAPTURE_OFFSET;
+    let top = -placement.bottom_edge();
+    let bottom = placement.bottom_edge();
+    let w_coordinates = Vec2::new(capture_start, top + WHITE_CAPTURE_POS); 
+    let b_coordinates: Vec2 = Vec2::new(capture_start, bottom + BLACK_CAPTURE_POS); 
 
     // Placeholder for Player 1 "profile".
     commands.spawn((
-        Sprite::from_image(asset_server.load("white_king.png")), 
-        Transform::from_xyz(CAPTURE_START, WHITE_PLAYER_POS, 1.0), 
+        Sprite::default(), 
+        Transform::from_xyz(capture_start, bottom + WHITE_PLAYER_POS, 1.0), 
+        PieceIcon { piece: PieceType::King, side: Side::White },
     )); 
 
     // Placeholder for Player 2 "profile".
     commands.spawn((
-        Sprite::from_image(asset_server.load("black_king.png")), 
-        Transform::from_xyz(CAPTURE_START, BLACK_PLAYER_POS, 1.0),
+        Sprite::default(), 
+        Transform::from_xyz(capture_start, top + BLACK_PLAYER_POS, 1.0),
+        PieceIcon { piece: PieceType::King, side: Side::Black },
     )); 
 
     commands.insert_resource( CaptureZones {
         white_pos: w_coordinates,
         black_pos: b_coordinates,
+        start_x: capture_start,
     }); 
 }
 
 #[derive(Resource)]
 pub struct Placement {
     pub positions: HashMap<BoardPosition, Coordinates>,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Placement {
+    // Centre of the bottom left square; the board is centred on the origin. 
+    fn start(&self) -> Vec2 {
+        Vec2::new(
+            -(self.width - 1) as f32 * TILE_SIZE / 2.0,
+            -(self.height - 1) as f32 * TILE_SIZE / 2.0,
+        )
+    }
+
+    pub fn left_edge(&self) -> f32 {
+        -self.width as f32 * TILE_SIZE / 2.0
+    }
+
+    pub fn bottom_edge(&self) -> f32 {
+        -self.height as f32 * TILE_SIZE / 2.0
+    }
+
+    pub fn contains(&self, (col, row): Square) -> bool {
+        (0..self.width).contains(&col) && (0..self.height).contains(&row)
+    }
+
+    // Square under a world position, or None when it is off the board. 
+    pub fn square_at(&self, pos: Vec2) -> Option<Square> {
+        let start = self.start();
+        let col = ((pos.x - start.x) / TILE_SIZE).round() as i32;
+        let row = ((pos.y - start.y) / TILE_SIZE).round() as i32;
+
+        if self.contains((col, row)) {
+            Some((col, row))
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn square_center(&self, (col, row): Square) -> Vec2 {
+        self.start() + Vec2::new(col as f32, row as f32) * TILE_SIZE
+    }
 }
 
-pub fn setup_placement(mut commands: Commands) {
-    let mut map: HashMap<String, (f32, f32)> = HashMap::new(); 
-    let mut y_pos: f32 = START_POS;
+pub fn setup_placement(mut commands: Commands, size: Res<BoardSize>) {
+    let mut placement = Placement {
+        positions: HashMap::new(),
+        width: size.width as i32,
+        height: size.height as i32,
+    };
+    let start = placement.start();
+    let mut y_pos: f32 = start.y;
     
-    for row in 0..8 {
-        let mut x_pos: f32 = START_POS;
+    for row in 0..placement.height {
+        let mut x_pos: f32 = start.x;
          
-        for col in 1..=8 {
-            let row_letter = (b'A' + row) as char;
-            map.insert(format!("{}{}", row_letter, col), (x_pos, y_pos)); 
-            x_pos += 100.0; 
+        for col in 0..placement.width {
+            placement.positions.insert(square_name((col, row)), (x_pos, y_pos)); 
+            x_pos += TILE_SIZE; 
         }
-        y_pos += 100.0;
+        y_pos += TILE_SIZE;
     }
 
-    commands.insert_resource(Placement {positions: map});
+    commands.insert_resource(placement);
+}
+
+// Algebraic name of a square: file letter then rank number, e.g. (4, 1) is "e2".
+pub fn square_name((col, row): Square) -> String {
+    format!("{}{}", file_name(col), row + 1)
+}
+
+pub fn file_name(col: i32) -> char {
+    (b'a' + col as u8) as char
 }
 
 pub struct PositionPlugin;
@@ -1,15 +1,22 @@
 use bevy::prelude::*; 
+use super::controls::*;
 use super::pieces::*;
 use super::position::*;
+use super::variants::*;
 
 pub struct ResetPlugin; 
 impl Plugin for ResetPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, reset);
+            .add_event::<NewGame>()
+            .add_systems(Update, (reset_key, reset).chain());
     }
 }
 
+// Sent to restart the game, e.g. after picking a Chess960 setup or a variant.
+#[derive(Event)]
+pub struct NewGame;
+
 struct Helper; 
 impl Helper {
     fn despawn_pieces<T: Component> (
@@ -22,28 +29,41 @@ impl Helper {
     }
 }
 
+fn reset_key(
+    keys: Res<ButtonInput<KeyCode>>, 
+    bindings: Res<Bindings>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if bindings.pressed(&keys, Action::ResetBoard) {
+        new_game.write(NewGame);
+    }
+}
+
 // Despawn black pieces and white pieces. 
 fn reset(
     mut commands: Commands, 
     black_query: Query<Entity, With<BlackPiece>>, 
     white_query: Query<Entity, With<WhitePiece>>, 
-    keys: Res<ButtonInput<KeyCode>>, 
-    asset_server: Res<AssetServer>,
+    mut new_game: EventReader<NewGame>,
     board: Res<Placement>,
+    back_rank: Res<BackRank>,
+    mut variant: ResMut<ActiveVariant>,
 ) {
-        if keys.pressed(KeyCode::Escape) {
+        if new_game.read().count() > 0 {
             Helper::despawn_pieces::<BlackPiece>(&mut commands, black_query); 
             Helper::despawn_pieces::<WhitePiece>(&mut commands, white_query); 
-            reset_pieces(commands, asset_server, board);
+            variant.rules.reset();
+            variant.result = None;
+            reset_pieces(commands, board, back_rank, variant);
         }
 }
 
 fn reset_pieces(
     mut commands: Commands, 
-    asset_server: Res<AssetServer>,
     board: Res<Placement>,
+    back_rank: Res<BackRank>,
+    variant: ResMut<ActiveVariant>,
 ) {
-    spawn_white_pieces(&mut commands, &asset_server, &board);
-    spawn_pawns(&mut commands, &asset_server, &board);
-    spawn_black_pieces(&mut commands, &asset_server, &board);
+    let position = variant.rules.starting_position(&back_rank, &board);
+    spawn_position(&mut commands, &board, &position);
 }
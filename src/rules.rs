@@ -0,0 +1,511 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use super::pieces::*;
+use super::position::*;
+
+const KNIGHT_JUMPS: [Square; 8] = [
+    (1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2),
+];
+const KING_STEPS: [Square; 8] = [
+    (0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1),
+];
+const ROOK_LINES: [Square; 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_LINES: [Square; 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Win(Side),
+}
+
+// What happens to a piece once it is captured.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CaptureEffect {
+    // Out of the game, lined up beside the board.
+    Remove,
+    // Switches sides and joins the captor's reserve.
+    Reserve,
+    // Blows up the captor and every piece but pawns around the square.
+    Explode,
+}
+
+// Rule set of a chess variant. Gameplay consults the active variant through
+// these hooks, so a new variant only needs a new implementation.
+pub trait Variant: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn starting_position(&self, back_rank: &BackRank, placement: &Placement) -> Vec<(Square, Side, PieceType)> {
+        standard_position(back_rank, placement)
+    }
+
+    // Whether a piece may go from one square to another. The board still
+    // shows the position before the move.
+    fn allows_move(&self, _board: &BoardState, _from: Square, _to: Square) -> bool {
+        true
+    }
+
+    fn capture_effect(&self) -> CaptureEffect {
+        CaptureEffect::Remove
+    }
+
+    // Whether a reserve piece may be dropped on the given empty square.
+    fn allows_drop(&self, _board: &BoardState, _side: Side, _piece: PieceType, _to: Square) -> bool {
+        false
+    }
+
+    // Called once a move is settled, captures included. Returning an
+    // outcome ends the game.
+    fn after_move(&mut self, _board: &BoardState, _mover: Side) -> Option<Outcome> {
+        None
+    }
+
+    // Clears any per-game state when the board is reset.
+    fn reset(&mut self) {}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+}
+
+impl CastlingRights {
+    // Castling field of a FEN string, "-" when nobody can castle.
+    pub fn fen(&self) -> String {
+        let mut field = String::new();
+        if self.white_kingside { field.push('K'); }
+        if self.white_queenside { field.push('Q'); }
+        if self.black_kingside { field.push('k'); }
+        if self.black_queenside { field.push('q'); }
+        if field.is_empty() { field.push('-'); }
+        field
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SetupError {
+    KingCount(Side, usize),
+    PawnOnBackRank(Square),
+    OpponentInCheck(Side),
+    CastlingRights(Side),
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetupError::KingCount(side, count) =>
+                write!(f, "{:?} needs exactly one king, found {}", side, count),
+            SetupError::PawnOnBackRank(square) =>
+                write!(f, "Pawn on back rank at {}", square_name(*square)),
+            SetupError::OpponentInCheck(side) =>
+                write!(f, "{:?} is in check but it is not their move", side),
+            SetupError::CastlingRights(side) =>
+                write!(f, "{:?} king and rook are not in place for the castling rights", side),
+        }
+    }
+}
+
+// Snapshot of the pieces on the board, used for rule checks.
+pub struct BoardState {
+    pub pieces: HashMap<Square, (Side, PieceType)>,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl BoardState {
+    // Builds the state from piece transforms, ignoring anything off the board.
+    pub fn collect<'a>(
+        placement: &Placement,
+        pieces: impl Iterator<Item = (&'a Transform, &'a PieceType, bool)>,
+    ) -> Self {
+        let mut map = HashMap::new();
+        for (transform, piece, is_white) in pieces {
+            if let Some(square) = placement.square_at(transform.translation.truncate()) {
+                let side = if is_white { Side::White } else { Side::Black };
+                map.insert(square, (side, *piece));
+            }
+        }
+
+        BoardState {
+            pieces: map,
+            width: placement.width,
+            height: placement.height,
+        }
+    }
+
+    pub fn get(&self, square: Square) -> Option<(Side, PieceType)> {
+        self.pieces.get(&square).copied()
+    }
+
+    fn on_board(&self, (col, row): Square) -> bool {
+        col >= 0 && col < self.width && row >= 0 && row < self.height
+    }
+
+    pub fn home_row(&self, side: Side) -> i32 {
+        match side {
+            Side::White => 0,
+            Side::Black => self.height - 1,
+        }
+    }
+
+    pub fn king(&self, side: Side) -> Option<Square> {
+        self.pieces
+            .iter()
+            .find(|(_, piece)| **piece == (side, PieceType::King))
+            .map(|(square, _)| *square)
+    }
+
+    pub fn is_attacked(&self, (col, row): Square, by: Side) -> bool {
+        // Pawns attack diagonally towards the opposing side.
+        let forward = if by == Side::White { 1 } else { -1 };
+        for side_step in [-1, 1] {
+            if self.get((col + side_step, row - forward)) == Some((by, PieceType::Pawn)) {
+                return true;
+            }
+        }
+
+        for (x, y) in KNIGHT_JUMPS {
+            if self.get((col + x, row + y)) == Some((by, PieceType::Knight)) {
+                return true;
+            }
+        }
+
+        for (x, y) in KING_STEPS {
+            if self.get((col + x, row + y)) == Some((by, PieceType::King)) {
+                return true;
+            }
+        }
+
+        let sliders = [
+            (ROOK_LINES, PieceType::Rook),
+            (BISHOP_LINES, PieceType::Bishop),
+        ];
+        for (lines, slider) in sliders {
+            for (x, y) in lines {
+                let mut square = (col + x, row + y);
+                while self.on_board(square) {
+                    if let Some((side, piece)) = self.get(square) {
+                        if side == by && (piece == slider || piece == PieceType::Queen) {
+                            return true;
+                        }
+                        break;
+                    }
+                    square = (square.0 + x, square.1 + y);
+                }
+            }
+        }
+
+        false
+    }
+
+    pub fn in_check(&self, side: Side) -> bool {
+        match self.king(side) {
+            Some(square) => self.is_attacked(square, side.opponent()),
+            None => false,
+        }
+    }
+
+    // Checks that a set up position can be played from.
+    pub fn validate(&self, side_to_move: Side, castling: &CastlingRights) -> Result<(), SetupError> {
+        for side in [Side::White, Side::Black] {
+            let kings = self.pieces
+                .values()
+                .filter(|piece| **piece == (side, PieceType::King))
+                .count();
+            if kings != 1 {
+                return Err(SetupError::KingCount(side, kings));
+            }
+        }
+
+        for (square, (_, piece)) in self.pieces.iter() {
+            if *piece == PieceType::Pawn && (square.1 == 0 || square.1 == self.height - 1) {
+                return Err(SetupError::PawnOnBackRank(*square));
+            }
+        }
+
+        let waiting = side_to_move.opponent();
+        if self.in_check(waiting) {
+            return Err(SetupError::OpponentInCheck(waiting));
+        }
+
+        let castles = [
+            (Side::White, 0, castling.white_kingside, castling.white_queenside),
+            (Side::Black, self.height - 1, castling.black_kingside, castling.black_queenside),
+        ];
+        for (side, row, kingside, queenside) in castles {
+            let king_home = self.get((self.width / 2, row)) == Some((side, PieceType::King));
+            let rook_at = |col| self.get((col, row)) == Some((side, PieceType::Rook));
+            if (kingside && !(king_home && rook_at(self.width - 1)))
+                || (queenside && !(king_home && rook_at(0)))
+            {
+                return Err(SetupError::CastlingRights(side));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Piece placement written out as a FEN string.
+    pub fn fen(&self, side_to_move: Side, castling: &str) -> String {
+        let mut placement = String::new();
+        for row in (0..self.height).rev() {
+            let mut empty = 0;
+            for col in 0..self.width {
+                match self.get((col, row)) {
+                    Some((side, piece)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(fen_letter(side, piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if row > 0 {
+                placement.push('/');
+            }
+        }
+
+        let turn = if side_to_move == Side::White { "w" } else { "b" };
+        format!("{} {} {} - 0 1", placement, turn, castling)
+    }
+
+    // Shredder-FEN castling field, naming the file of every castling rook.
+    pub fn shredder_castling(&self, rooks: &[(Side, i32)]) -> String {
+        let mut field = String::new();
+        for side in [Side::White, Side::Black] {
+            let mut cols: Vec<i32> = rooks
+                .iter()
+                .filter(|(rook_side, _)| *rook_side == side)
+                .map(|(_, col)| *col)
+                .collect();
+            cols.sort_by(|a, b| b.cmp(a));
+            for col in cols {
+                field.push(file_letter(side, col));
+            }
+        }
+        if field.is_empty() { field.push('-'); }
+        field
+    }
+
+    // X-FEN castling field. Uses KQkq like plain FEN, and only names the rook's
+    // file when another rook stands further out on the same wing.
+    pub fn xfen_castling(&self, rooks: &[(Side, i32)]) -> String {
+        let mut field = String::new();
+        for side in [Side::White, Side::Black] {
+            let Some((king_col, _)) = self.king(side) else {
+                continue;
+            };
+            let row = self.home_row(side);
+            let mut cols: Vec<i32> = rooks
+                .iter()
+                .filter(|(rook_side, _)| *rook_side == side)
+                .map(|(_, col)| *col)
+                .collect();
+            cols.sort_by(|a, b| b.cmp(a));
+
+            for col in cols {
+                let kingside = col > king_col;
+                let outside = if kingside { col + 1..self.width } else { 0..col };
+                let outermost = outside
+                    .into_iter()
+                    .all(|other| self.get((other, row)) != Some((side, PieceType::Rook)));
+                let letter = match (outermost, kingside) {
+                    (true, true) => 'K',
+                    (true, false) => 'Q',
+                    (false, _) => file_letter(Side::White, col),
+                };
+                field.push(if side == Side::White { letter } else { letter.to_ascii_lowercase() });
+            }
+        }
+        if field.is_empty() { field.push('-'); }
+        field
+    }
+}
+
+fn file_letter(side: Side, col: i32) -> char {
+    let letter = file_name(col);
+    if side == Side::White { letter.to_ascii_uppercase() } else { letter }
+}
+
+fn fen_letter(side: Side, piece: PieceType) -> char {
+    let letter = match piece {
+        PieceType::King => 'k',
+        PieceType::Queen => 'q',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::Rook => 'r',
+        PieceType::Pawn => 'p',
+    };
+    if side == Side::White { letter.to_ascii_uppercase() } else { letter }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    // Reads the placement field of a FEN string into an 8x8 board.
+    pub fn board(fen: &str) -> BoardState {
+        let mut pieces = HashMap::new();
+        let placement = fen.split(' ').next().unwrap();
+        for (rank, line) in placement.split('/').enumerate() {
+            let row = 7 - rank as i32;
+            let mut col = 0;
+            for letter in line.chars() {
+                if let Some(empty) = letter.to_digit(10) {
+                    col += empty as i32;
+                    continue;
+                }
+                let side = if letter.is_ascii_uppercase() { Side::White } else { Side::Black };
+                let piece = match letter.to_ascii_lowercase() {
+                    'k' => PieceType::King,
+                    'q' => PieceType::Queen,
+                    'r' => PieceType::Rook,
+                    'b' => PieceType::Bishop,
+                    'n' => PieceType::Knight,
+                    'p' => PieceType::Pawn,
+                    other => panic!("unexpected piece letter {}", other),
+                };
+                pieces.insert((col, row), (side, piece));
+                col += 1;
+            }
+        }
+        BoardState { pieces, width: 8, height: 8 }
+    }
+
+    fn no_castling() -> CastlingRights {
+        CastlingRights {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    #[test]
+    fn start_position_fen_round_trips() {
+        let start = board(START_FEN);
+        assert_eq!(start.fen(Side::White, &CastlingRights::default().fen()), START_FEN);
+        assert_eq!(start.validate(Side::White, &CastlingRights::default()), Ok(()));
+    }
+
+    #[test]
+    fn missing_king_is_rejected() {
+        let position = board("4k3/8/8/8/8/8/8/8");
+        assert_eq!(
+            position.validate(Side::White, &no_castling()),
+            Err(SetupError::KingCount(Side::White, 0)),
+        );
+    }
+
+    #[test]
+    fn extra_king_is_rejected() {
+        let position = board("k3k3/8/8/8/8/8/8/4K3");
+        assert_eq!(
+            position.validate(Side::White, &no_castling()),
+            Err(SetupError::KingCount(Side::Black, 2)),
+        );
+    }
+
+    #[test]
+    fn pawn_on_back_rank_is_rejected() {
+        let position = board("4k3/8/8/8/8/8/8/P3K3");
+        assert_eq!(
+            position.validate(Side::White, &no_castling()),
+            Err(SetupError::PawnOnBackRank((0, 0))),
+        );
+        let position = board("p3k3/8/8/8/8/8/8/4K3");
+        assert_eq!(
+            position.validate(Side::White, &no_castling()),
+            Err(SetupError::PawnOnBackRank((0, 7))),
+        );
+    }
+
+    #[test]
+    fn side_not_to_move_in_check_is_rejected() {
+        // The white rook on e2 gives check along the e-file.
+        let position = board("4k3/8/8/8/8/8/4R3/K7");
+        assert_eq!(
+            position.validate(Side::White, &no_castling()),
+            Err(SetupError::OpponentInCheck(Side::Black)),
+        );
+        assert_eq!(position.validate(Side::Black, &no_castling()), Ok(()));
+    }
+
+    #[test]
+    fn castling_needs_king_and_rook_at_home() {
+        let rights = |white_kingside, black_queenside| CastlingRights {
+            white_kingside,
+            black_queenside,
+            ..no_castling()
+        };
+
+        // No rook on h1.
+        let position = board("r3k3/8/8/8/8/8/8/4K3");
+        assert_eq!(
+            position.validate(Side::White, &rights(true, false)),
+            Err(SetupError::CastlingRights(Side::White)),
+        );
+        assert_eq!(position.validate(Side::White, &rights(false, true)), Ok(()));
+
+        // Black's king has left e8.
+        let position = board("r2k4/8/8/8/8/8/8/4K2R");
+        assert_eq!(position.validate(Side::White, &rights(true, false)), Ok(()));
+        assert_eq!(
+            position.validate(Side::White, &rights(false, true)),
+            Err(SetupError::CastlingRights(Side::Black)),
+        );
+    }
+
+    #[test]
+    fn attacks_are_blocked_by_pieces_in_between() {
+        let position = board("4k3/8/8/8/8/8/4R3/K7");
+        assert!(position.is_attacked((4, 7), Side::White));
+        let blocked = board("4k3/8/8/4n3/8/8/4R3/K7");
+        assert!(!blocked.is_attacked((4, 7), Side::White));
+        // Pawns only attack diagonally forwards.
+        let pawn = board("4k3/8/8/8/8/8/3P4/K7");
+        assert!(pawn.is_attacked((4, 2), Side::White));
+        assert!(!pawn.is_attacked((3, 2), Side::White));
+        assert!(!pawn.is_attacked((4, 0), Side::White));
+    }
+
+    #[test]
+    fn castling_fields_of_the_start_position() {
+        let start = board(START_FEN);
+        let rooks = [(Side::White, 0), (Side::White, 7), (Side::Black, 0), (Side::Black, 7)];
+        assert_eq!(start.xfen_castling(&rooks), "KQkq");
+        assert_eq!(start.shredder_castling(&rooks), "HAha");
+        assert_eq!(start.xfen_castling(&[]), "-");
+        assert_eq!(start.shredder_castling(&[]), "-");
+    }
+
+    #[test]
+    fn xfen_names_the_file_of_an_inner_rook() {
+        // White may castle with the b1 rook, but another rook stands on a1.
+        // Black's g8 rook is the outermost on its wing.
+        let position = board("1k4r1/8/8/8/8/8/8/RRK5");
+        let rooks = [(Side::White, 1), (Side::Black, 6)];
+        assert_eq!(position.xfen_castling(&rooks), "Bk");
+        assert_eq!(position.shredder_castling(&rooks), "Bg");
+    }
+}
@@ -0,0 +1,387 @@
+use bevy::prelude::*;
+use bevy::asset::LoadState;
+use bevy::input::InputSystem;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use super::animation::AnimationSettings;
+use super::audio::SoundSettings;
+use super::controls::*;
+use super::theme::{Theme, Themes};
+use super::window::*;
+
+const SETTINGS_FILE: &str = "settings.ron";
+const VOLUME_STEP: f32 = 0.1;
+const SPEED_STEP: f32 = 0.25;
+const MAX_SPEED: f32 = 4.0;
+// Rows for the settings themselves, followed by one row per key binding.
+const SETTING_ROWS: usize = 6;
+const MENU_ROWS: usize = SETTING_ROWS + Action::ALL.len();
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = Settings::load();
+        app
+            .insert_resource(settings.bindings.clone())
+            .insert_resource(settings)
+            .init_resource::<SettingsMenu>()
+            .add_systems(Startup, (apply_settings, spawn_menu))
+            // Runs straight after input is read, so keys the menu uses are
+            // taken away before the rest of the game sees them.
+            .add_systems(PreUpdate, menu_keys.after(InputSystem))
+            .add_systems(Update, (prefer_theme, update_menu, store_settings).chain());
+    }
+}
+
+// `<config dir>/bevy_chess`, where settings and user themes are kept.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("bevy_chess"))
+}
+
+// Everything remembered between runs, stored as settings.ron in the config
+// directory. Missing fields fall back to their defaults.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub window_mode: DisplayMode,
+    pub resolution: Option<Resolution>,
+    pub theme: String,
+    pub volume: f32,
+    pub muted: bool,
+    pub animation_speed: f32,
+    pub bindings: Bindings,
+    // Speed given with `--animation-speed`, overriding the stored one until
+    // it is changed in the menu.
+    #[serde(skip)]
+    cli_speed: Option<f32>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let window = WindowSettings::default();
+        let sound = SoundSettings::default();
+        Settings {
+            window_mode: window.mode,
+            resolution: window.resolution,
+            theme: Theme::default().name,
+            volume: sound.volume,
+            muted: sound.muted,
+            animation_speed: AnimationSettings::default().speed,
+            bindings: Bindings::default(),
+            cli_speed: None,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(SETTINGS_FILE))
+    }
+
+    fn load() -> Self {
+        let Some(path) = Settings::path() else {
+            return Settings::default();
+        };
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Settings::default();
+        };
+        let mut settings: Settings = ron::from_str(&text).unwrap_or_else(|err| {
+            warn!("Ignoring {}: {}", path.display(), err);
+            Settings::default()
+        });
+        settings.bindings.resolve_conflicts();
+        settings
+    }
+
+    fn save(&self) {
+        let Some(path) = Settings::path() else {
+            return;
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|err| err.to_string())
+            .and_then(|_| {
+                ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                    .map_err(|err| err.to_string())
+            })
+            .and_then(|text| fs::write(&path, text).map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            error!("Could not save {}: {}", path.display(), err);
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SettingsMenu {
+    pub open: bool,
+    selected: usize,
+    // Waiting for the key to bind to the selected action.
+    listening: bool,
+    message: String,
+}
+
+#[derive(Component)]
+struct SettingsText;
+
+// Hands the stored settings to the modules that own them. An animation speed
+// given on the command line wins over the stored one for this run only.
+fn apply_settings(
+    mut settings: ResMut<Settings>,
+    mut window: ResMut<WindowSettings>,
+    mut sound: ResMut<SoundSettings>,
+    mut animation: ResMut<AnimationSettings>,
+) {
+    window.mode = settings.window_mode;
+    window.resolution = settings.resolution;
+    sound.volume = settings.volume;
+    sound.muted = settings.muted;
+    settings.cli_speed = AnimationSettings::from_args().map(|args| args.speed);
+    if settings.cli_speed.is_none() {
+        animation.speed = settings.animation_speed;
+    }
+}
+
+// Themes load in the background, so the stored one is picked once it arrives.
+fn prefer_theme(
+    mut events: EventReader<AssetEvent<Theme>>,
+    settings: Res<Settings>,
+    mut themes: ResMut<Themes>,
+    theme_assets: Res<Assets<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    let loaded = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::LoadedWithDependencies { .. }));
+    if loaded && theme.name != settings.theme {
+        themes.select(&settings.theme, &theme_assets, &mut theme);
+    }
+}
+
+// Writes the settings file whenever something it covers changes. A speed
+// given on the command line is not written over the stored one, but one
+// picked in the menu afterwards is.
+#[allow(clippy::too_many_arguments)]
+fn store_settings(
+    mut settings: ResMut<Settings>,
+    window: Res<WindowSettings>,
+    sound: Res<SoundSettings>,
+    animation: Res<AnimationSettings>,
+    bindings: Res<Bindings>,
+    theme: Res<Theme>,
+    themes: Res<Themes>,
+    asset_server: Res<AssetServer>,
+) {
+    if !(window.is_changed() || sound.is_changed() || animation.is_changed()
+        || bindings.is_changed() || theme.is_changed())
+    {
+        return;
+    }
+
+    // Until the stored theme has had its chance to load, keep its name.
+    let themes_settled = themes.handles.iter().all(|handle| {
+        matches!(asset_server.load_state(handle.id()), LoadState::Loaded | LoadState::Failed(_))
+    });
+    let theme_name = if themes_settled { theme.name.clone() } else { settings.theme.clone() };
+    let cli_speed = settings.cli_speed.filter(|speed| *speed == animation.speed);
+
+    let current = Settings {
+        window_mode: window.mode,
+        resolution: window.resolution,
+        theme: theme_name,
+        volume: sound.volume,
+        muted: sound.muted,
+        animation_speed: if cli_speed.is_some() { settings.animation_speed } else { animation.speed },
+        bindings: bindings.clone(),
+        cli_speed,
+    };
+    if current != *settings {
+        *settings = current;
+        settings.save();
+    }
+}
+
+fn spawn_menu(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(5.0),
+            left: Val::Percent(35.0),
+            padding: UiRect::all(Val::Px(16.0)),
+            ..default()
+        },
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+        SettingsText,
+    ));
+}
+
+// O opens and closes the menu, Escape closes it too. Up and Down pick a row,
+// Left and Right change a setting and Enter rebinds an action to the next key
+// pressed, or cancels with Escape. No key reaches the game while it is open.
+#[allow(clippy::too_many_arguments)]
+fn menu_keys(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut bindings: ResMut<Bindings>,
+    mut menu: ResMut<SettingsMenu>,
+    mut window: ResMut<WindowSettings>,
+    mut sound: ResMut<SoundSettings>,
+    mut animation: ResMut<AnimationSettings>,
+    mut themes: ResMut<Themes>,
+    theme_assets: Res<Assets<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    if menu.listening {
+        let Some(key) = keys.get_just_pressed().next().copied() else {
+            return;
+        };
+        keys.reset(key);
+        menu.listening = false;
+        let action = Action::ALL[menu.selected - SETTING_ROWS];
+        if key == KeyCode::Escape {
+            menu.message.clear();
+            return;
+        }
+        menu.message = if let Some(reason) = reserved_use(key) {
+            format!("{} is reserved for {}", key_name(key), reason)
+        }
+        else {
+            match bindings.action_for(key) {
+                Some(other) if other == action => String::new(),
+                Some(other) => format!("{} is already used by {}", key_name(key), other.description()),
+                None => {
+                    bindings.bind(action, key);
+                    String::new()
+                }
+            }
+        };
+        return;
+    }
+
+    if menu.open && keys.just_pressed(KeyCode::Escape) {
+        keys.reset(KeyCode::Escape);
+        menu.open = false;
+        menu.message.clear();
+        return;
+    }
+    if bindings.just_pressed(&keys, Action::Settings) {
+        menu.open = !menu.open;
+        menu.message.clear();
+    }
+    if !menu.open {
+        return;
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = (menu.selected + MENU_ROWS - 1) % MENU_ROWS;
+        menu.message.clear();
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1) % MENU_ROWS;
+        menu.message.clear();
+    }
+    if menu.selected >= SETTING_ROWS && keys.just_pressed(KeyCode::Enter) {
+        keys.reset(KeyCode::Enter);
+        menu.listening = true;
+        menu.message.clear();
+    }
+
+    let step: isize = if keys.just_pressed(KeyCode::ArrowRight) {
+        1
+    }
+    else if keys.just_pressed(KeyCode::ArrowLeft) {
+        -1
+    }
+    else {
+        0
+    };
+    for key in MENU_KEYS.into_iter().chain(Action::ALL.map(|action| bindings.key(action))) {
+        keys.reset(key);
+    }
+    if step == 0 {
+        return;
+    }
+    match menu.selected {
+        0 => {
+            window.mode = match window.mode {
+                DisplayMode::Windowed => DisplayMode::Fullscreen,
+                DisplayMode::Fullscreen => DisplayMode::Windowed,
+            };
+        }
+        1 => {
+            let sizes = [Resolution::Small, Resolution::Medium, Resolution::Large];
+            let index = window
+                .resolution
+                .and_then(|current| sizes.iter().position(|size| *size == current))
+                .map_or(if step > 0 { 0 } else { sizes.len() - 1 }, |index| {
+                    (index as isize + step).rem_euclid(sizes.len() as isize) as usize
+                });
+            window.resolution = Some(sizes[index]);
+        }
+        2 => themes.cycle(step, &theme_assets, &mut theme),
+        3 => sound.muted = !sound.muted,
+        4 => sound.volume = (sound.volume + step as f32 * VOLUME_STEP).clamp(0.0, 1.0),
+        5 => animation.speed = (animation.speed + step as f32 * SPEED_STEP).clamp(0.0, MAX_SPEED),
+        _ => {}
+    }
+}
+
+fn update_menu(
+    menu: Res<SettingsMenu>,
+    window: Res<WindowSettings>,
+    sound: Res<SoundSettings>,
+    animation: Res<AnimationSettings>,
+    bindings: Res<Bindings>,
+    theme: Res<Theme>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<SettingsText>>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.single_mut() else {
+        return;
+    };
+    let shown = if menu.open { Visibility::Inherited } else { Visibility::Hidden };
+    if *visibility != shown {
+        *visibility = shown;
+    }
+    if !menu.open {
+        return;
+    }
+
+    let speed = if animation.speed > 0.0 { format!("{}x", animation.speed) } else { "Off".to_string() };
+    let mut rows = vec![
+        format!("Window: {:?}", window.mode),
+        format!("Resolution: {}", window.resolution.map_or("Window size".to_string(), |size| format!("{:?}", size))),
+        format!("Theme: {}", theme.name),
+        format!("Sound: {}", if sound.muted { "Off" } else { "On" }),
+        format!("Volume: {:.0}%", sound.volume * 100.0),
+        format!("Animation speed: {}", speed),
+    ];
+    for (row, action) in Action::ALL.into_iter().enumerate() {
+        let key = if menu.listening && row + SETTING_ROWS == menu.selected {
+            "press a key, Escape to cancel".to_string()
+        }
+        else {
+            bindings.name(action)
+        };
+        rows.push(format!("{}: {}", action.description(), key));
+    }
+
+    let mut menu_text = format!("Settings ({} or Escape to close, Enter to rebind)\n", bindings.name(Action::Settings));
+    for (row, line) in rows.iter().enumerate() {
+        let marker = if row == menu.selected { "> " } else { "  " };
+        menu_text += &format!("\n{}{}", marker, line);
+        if row + 1 == SETTING_ROWS {
+            menu_text += "\n\nKeys";
+        }
+    }
+    if !menu.message.is_empty() {
+        menu_text += &format!("\n\n{}", menu.message);
+    }
+    if text.0 != menu_text {
+        text.0 = menu_text;
+    }
+}
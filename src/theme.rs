@@ -0,0 +1,341 @@
+use bevy::prelude::*;
+use bevy::asset::io::{AssetSource, AssetSourceBuilder, AssetSourceId, Reader};
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::{AssetLoadFailedEvent, AssetLoader, LoadContext};
+use bevy_ecs_tilemap::prelude::*;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use super::controls::*;
+use super::pieces::*;
+use super::settings::config_dir;
+
+// Theme files shipped with the game, one RON file per theme. Relative to
+// the asset server's base path, so it is found wherever the game is run from.
+const THEME_DIR: &str = "assets/themes";
+const DEFAULT_THEME: &str = "themes/nature.ron";
+// Asset source for theme folders the user drops into their config directory.
+const USER_SOURCE: &str = "user";
+const USER_THEME_FILE: &str = "theme.ron";
+
+// Registers the user theme directory as an asset source. Has to be added
+// before DefaultPlugins, which sets up the asset server.
+pub struct UserThemeSourcePlugin;
+impl Plugin for UserThemeSourcePlugin {
+    fn build(&self, app: &mut App) {
+        let Some(dir) = user_theme_dir() else {
+            return;
+        };
+        if let Err(err) = fs::create_dir_all(&dir) {
+            warn!("Could not create {}: {}", dir.display(), err);
+            return;
+        }
+        let path = dir.to_string_lossy().to_string();
+        app.register_asset_source(
+            USER_SOURCE,
+            AssetSourceBuilder::default()
+                .with_reader(AssetSource::get_default_reader(path.clone()))
+                .with_watcher(AssetSource::get_default_watcher(path, Duration::from_millis(300))),
+        );
+    }
+}
+
+pub struct ThemePlugin;
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .init_resource::<Theme>()
+            .add_systems(Startup, discover_themes)
+            .add_systems(
+                Update,
+                (switch_theme, apply_theme, restyle_board, report_missing_images).chain(),
+            );
+    }
+}
+
+// `<config dir>/themes`, holding one folder per theme with a
+// theme.ron and the images it names next to it.
+pub fn user_theme_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("themes"))
+}
+
+// The twelve piece images of one side.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PieceSet {
+    pub king: String,
+    pub queen: String,
+    pub rook: String,
+    pub bishop: String,
+    pub knight: String,
+    pub pawn: String,
+}
+
+impl PieceSet {
+    fn image(&self, piece: PieceType) -> &str {
+        match piece {
+            PieceType::King => &self.king,
+            PieceType::Queen => &self.queen,
+            PieceType::Rook => &self.rook,
+            PieceType::Bishop => &self.bishop,
+            PieceType::Knight => &self.knight,
+            PieceType::Pawn => &self.pawn,
+        }
+    }
+}
+
+// Square textures, highlight tints and piece images the game is drawn with.
+// Colours are RGB from 0 to 1. Images of the shipped themes are paths inside
+// `assets/`, those of user themes are relative to the theme's folder.
+#[derive(Asset, TypePath, Resource, Deserialize, Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub dark_square: String,
+    pub light_square: String,
+    pub last_move: (f32, f32, f32),
+    pub check: (f32, f32, f32),
+    pub white: PieceSet,
+    pub black: PieceSet,
+    // Prefix turning the image names into asset paths, set by the loader.
+    #[serde(skip)]
+    root: String,
+}
+
+impl Theme {
+    pub fn image_path(&self, image: &str) -> String {
+        format!("{}{}", self.root, image)
+    }
+
+    pub fn piece_image(&self, piece: PieceType, side: Side) -> String {
+        match side {
+            Side::White => self.image_path(self.white.image(piece)),
+            Side::Black => self.image_path(self.black.image(piece)),
+        }
+    }
+
+    pub fn last_move_color(&self) -> Color {
+        let (r, g, b) = self.last_move;
+        Color::srgb(r, g, b)
+    }
+
+    pub fn check_color(&self) -> Color {
+        let (r, g, b) = self.check;
+        Color::srgb(r, g, b)
+    }
+}
+
+// Drawn with until the theme files have loaded, and whenever none can be.
+impl Default for Theme {
+    fn default() -> Self {
+        let set = |pieces: [&str; 6]| PieceSet {
+            king: pieces[0].to_string(),
+            queen: pieces[1].to_string(),
+            rook: pieces[2].to_string(),
+            bishop: pieces[3].to_string(),
+            knight: pieces[4].to_string(),
+            pawn: pieces[5].to_string(),
+        };
+        Theme {
+            name: "Nature".to_string(),
+            dark_square: "dirt.png".to_string(),
+            light_square: "grass.png".to_string(),
+            last_move: (1.0, 1.0, 0.55),
+            check: (1.0, 0.45, 0.45),
+            white: set(["white_king.png", "white_queen.png", "white_rook.png", "ladybug.png", "white_knight.png", "caterpillar.png"]),
+            black: set(["black_king.png", "black_queen.png", "black_rook.png", "black_bishop.png", "clove.png", "dandelion.png"]),
+            root: String::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "could not read theme: {}", err),
+            ThemeError::Ron(err) => write!(f, "invalid theme: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+#[derive(Default)]
+struct ThemeLoader;
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+    type Settings = ();
+    type Error = ThemeError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Theme, ThemeError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(ThemeError::Io)?;
+        let mut theme: Theme = ron::de::from_bytes(&bytes).map_err(ThemeError::Ron)?;
+
+        // Images of user themes live beside their theme.ron.
+        let path = load_context.asset_path();
+        if let (AssetSourceId::Name(_), Some(folder)) = (path.source(), path.parent()) {
+            theme.root = format!("{}/", folder);
+        }
+        Ok(theme)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+// Every theme found at startup; the active one is copied into the Theme
+// resource once loaded, and again whenever its file changes on disk.
+#[derive(Resource)]
+pub struct Themes {
+    pub handles: Vec<Handle<Theme>>,
+    pub current: usize,
+}
+
+impl Themes {
+    // Moves `step` themes along, wrapping around, and makes that one active.
+    pub fn cycle(&mut self, step: isize, theme_assets: &Assets<Theme>, theme: &mut Theme) {
+        if self.handles.is_empty() {
+            return;
+        }
+        let count = self.handles.len() as isize;
+        self.current = (self.current as isize + step).rem_euclid(count) as usize;
+        let handle = &self.handles[self.current];
+        match theme_assets.get(handle) {
+            Some(next) => {
+                *theme = next.clone();
+                info!("Theme: {}", theme.name);
+            }
+            None => error!("Theme {:?} is not loaded, see the log for why", handle.path()),
+        }
+    }
+
+    // Makes the theme with the given name active once it has loaded.
+    pub fn select(&mut self, name: &str, theme_assets: &Assets<Theme>, theme: &mut Theme) {
+        let found = self.handles.iter().position(|handle| {
+            theme_assets.get(handle).is_some_and(|loaded| loaded.name == name)
+        });
+        if let Some(index) = found
+            && (index != self.current || theme.name != name)
+        {
+            self.current = index;
+            *theme = theme_assets.get(&self.handles[index]).cloned().unwrap_or_default();
+        }
+    }
+}
+
+fn discover_themes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let file_names = |dir: &PathBuf| -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_else(|err| {
+                error!("Could not read {}: {}", dir.display(), err);
+                Vec::new()
+            });
+        names.sort();
+        names
+    };
+
+    let mut paths: Vec<String> = file_names(&FileAssetReader::get_base_path().join(THEME_DIR))
+        .into_iter()
+        .filter(|name| name.ends_with(".ron"))
+        .map(|name| format!("themes/{}", name))
+        .collect();
+
+    if let Some(dir) = user_theme_dir() {
+        for folder in file_names(&dir) {
+            if dir.join(&folder).join(USER_THEME_FILE).is_file() {
+                paths.push(format!("{}://{}/{}", USER_SOURCE, folder, USER_THEME_FILE));
+            }
+        }
+    }
+
+    info!("Found {} theme(s)", paths.len());
+    let current = paths.iter().position(|path| path == DEFAULT_THEME).unwrap_or(0);
+    commands.insert_resource(Themes {
+        handles: paths.into_iter().map(|path| asset_server.load(path)).collect(),
+        current,
+    });
+}
+
+// T switches to the next theme without touching the game.
+fn switch_theme(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut themes: ResMut<Themes>,
+    theme_assets: Res<Assets<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    if bindings.just_pressed(&keys, Action::SwitchTheme) {
+        themes.cycle(1, &theme_assets, &mut theme);
+    }
+}
+
+fn apply_theme(
+    mut events: EventReader<AssetEvent<Theme>>,
+    themes: Option<Res<Themes>>,
+    theme_assets: Res<Assets<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    let Some(themes) = themes else {
+        return;
+    };
+    let Some(handle) = themes.handles.get(themes.current) else {
+        return;
+    };
+    for event in events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } = event
+            && *id == handle.id()
+            && let Some(loaded) = theme_assets.get(*id)
+        {
+            *theme = loaded.clone();
+            info!("Theme: {}", theme.name);
+        }
+    }
+}
+
+fn restyle_board(
+    theme: Res<Theme>,
+    asset_server: Res<AssetServer>,
+    mut tilemap_query: Query<&mut TilemapTexture>,
+) {
+    if !theme.is_changed() || theme.is_added() {
+        return;
+    }
+    for mut texture in tilemap_query.iter_mut() {
+        *texture = TilemapTexture::Vector(vec![
+            asset_server.load(theme.image_path(&theme.dark_square)),
+            asset_server.load(theme.image_path(&theme.light_square)),
+        ]);
+    }
+}
+
+// Names every image that failed to load. The piece atlas shows a coloured
+// square in its place, so a broken theme never leaves pieces invisible.
+fn report_missing_images(
+    mut failures: EventReader<AssetLoadFailedEvent<Image>>,
+    theme: Res<Theme>,
+) {
+    for failure in failures.read() {
+        error!("Theme {}: could not load image {}: {}", theme.name, failure.path, failure.error);
+    }
+}
@@ -0,0 +1,312 @@
+use bevy::prelude::*;
+use super::controls::*;
+use super::gameplay::MoveMade;
+use super::pieces::*;
+use super::position::*;
+use super::reset::NewGame;
+use super::rules::*;
+
+const VARIANT_COUNT: usize = 5;
+
+pub struct VariantsPlugin;
+impl Plugin for VariantsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ActiveVariant>()
+            .add_systems(Startup, spawn_variant_text)
+            .add_systems(Update, (select_variant, judge_move, update_variant_text).chain());
+    }
+}
+
+#[derive(Resource)]
+pub struct ActiveVariant {
+    index: usize,
+    pub rules: Box<dyn Variant>,
+    pub result: Option<Outcome>,
+}
+
+impl Default for ActiveVariant {
+    fn default() -> Self {
+        ActiveVariant {
+            index: 0,
+            rules: variant_by_index(0),
+            result: None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct VariantText;
+
+// Once a variant's win condition is met, no more pieces may be picked up.
+pub fn game_running(variant: Res<ActiveVariant>) -> bool {
+    variant.result.is_none()
+}
+
+fn variant_by_index(index: usize) -> Box<dyn Variant> {
+    match index % VARIANT_COUNT {
+        1 => Box::new(KingOfTheHill),
+        2 => Box::new(ThreeCheck::default()),
+        3 => Box::new(Crazyhouse),
+        4 => Box::new(Atomic),
+        _ => Box::new(Standard),
+    }
+}
+
+pub struct Standard;
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+// Bringing your king to one of the four centre squares wins.
+pub struct KingOfTheHill;
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn after_move(&mut self, board: &BoardState, mover: Side) -> Option<Outcome> {
+        let (col, row) = board.king(mover)?;
+        let on_hill = |value: i32, length: i32| value == length / 2 - 1 || value == length / 2;
+        if on_hill(col, board.width) && on_hill(row, board.height) {
+            Some(Outcome::Win(mover))
+        }
+        else {
+            None
+        }
+    }
+}
+
+// Giving check for the third time wins.
+#[derive(Default)]
+pub struct ThreeCheck {
+    checks: [u8; 2],
+}
+
+impl ThreeCheck {
+    fn checks_by(&mut self, side: Side) -> &mut u8 {
+        match side {
+            Side::White => &mut self.checks[0],
+            Side::Black => &mut self.checks[1],
+        }
+    }
+}
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "Three-Check"
+    }
+
+    fn after_move(&mut self, board: &BoardState, mover: Side) -> Option<Outcome> {
+        if !board.in_check(mover.opponent()) {
+            return None;
+        }
+        let checks = self.checks_by(mover);
+        *checks += 1;
+        info!("{:?} has given check {} time(s)", mover, checks);
+        if *checks >= 3 { Some(Outcome::Win(mover)) } else { None }
+    }
+
+    fn reset(&mut self) {
+        self.checks = [0; 2];
+    }
+}
+
+// Captured pieces join the captor's reserve and can be dropped back in as a move.
+pub struct Crazyhouse;
+impl Variant for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    fn capture_effect(&self) -> CaptureEffect {
+        CaptureEffect::Reserve
+    }
+
+    // Pawns may not be dropped on the first or last rank.
+    fn allows_drop(&self, board: &BoardState, _side: Side, piece: PieceType, (_, row): Square) -> bool {
+        piece != PieceType::Pawn || (row != 0 && row != board.height - 1)
+    }
+}
+
+// Captures explode, taking the captor and nearby pieces with them. Blowing
+// up the enemy king wins, see the explosions module.
+pub struct Atomic;
+impl Variant for Atomic {
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn capture_effect(&self) -> CaptureEffect {
+        CaptureEffect::Explode
+    }
+
+    // Kings cannot capture, and no capture may blow up your own king.
+    fn allows_move(&self, board: &BoardState, from: Square, to: Square) -> bool {
+        let (Some((side, piece)), Some((target_side, _))) = (board.get(from), board.get(to)) else {
+            return true;
+        };
+        if target_side == side {
+            return true;
+        }
+        if piece == PieceType::King {
+            return false;
+        }
+        board
+            .king(side)
+            .is_none_or(|(col, row)| (col - to.0).abs() > 1 || (row - to.1).abs() > 1)
+    }
+}
+
+fn select_variant(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut variant: ResMut<ActiveVariant>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    if bindings.just_pressed(&keys, Action::SwitchVariant) {
+        let index = (variant.index + 1) % VARIANT_COUNT;
+        *variant = ActiveVariant {
+            index,
+            rules: variant_by_index(index),
+            result: None,
+        };
+        new_game.write(NewGame);
+    }
+}
+
+pub fn judge_move(
+    mut moves: EventReader<MoveMade>,
+    mut variant: ResMut<ActiveVariant>,
+    placement: Res<Placement>,
+    piece_query: Query<(&Transform, &PieceType, Has<WhitePiece>), With<Movable>>,
+) {
+    for made in moves.read() {
+        if variant.result.is_some() {
+            continue;
+        }
+        let board = BoardState::collect(&placement, piece_query.iter());
+        let result = variant.rules.after_move(&board, made.side);
+        variant.result = result;
+    }
+}
+
+fn spawn_variant_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        VariantText,
+    ));
+}
+
+// Lists every variant with the active one in brackets, so the choice is on
+// screen rather than hidden behind the switch key.
+fn update_variant_text(
+    variant: Res<ActiveVariant>,
+    bindings: Res<Bindings>,
+    mut text_query: Query<&mut Text, With<VariantText>>,
+) {
+    if !variant.is_changed() && !bindings.is_changed() {
+        return;
+    }
+    let choices: Vec<String> = (0..VARIANT_COUNT)
+        .map(|index| {
+            let name = variant_by_index(index).name();
+            if index == variant.index { format!("[{}]", name) } else { name.to_string() }
+        })
+        .collect();
+    let result = match variant.result {
+        Some(Outcome::Win(side)) => format!("\n{:?} wins!", side),
+        None => String::new(),
+    };
+    for mut text in text_query.iter_mut() {
+        text.0 = format!(
+            "Variant: {}{}\n{}\nPress {} for the next variant and a new game",
+            variant.rules.name(),
+            result,
+            choices.join(" | "),
+            bindings.name(Action::SwitchVariant),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::rules::tests::board;
+
+    #[test]
+    fn king_of_the_hill_is_won_on_a_centre_square() {
+        let mut rules = KingOfTheHill;
+        for centre in ["4k3/8/8/8/3K4/8/8/8", "4k3/8/8/4K3/8/8/8/8"] {
+            assert_eq!(rules.after_move(&board(centre), Side::White), Some(Outcome::Win(Side::White)));
+        }
+        let off_centre = board("4k3/8/8/8/2K5/8/8/8");
+        assert_eq!(rules.after_move(&off_centre, Side::White), None);
+        // Only the mover's king counts.
+        let black_on_hill = board("8/8/8/3k4/8/8/8/4K3");
+        assert_eq!(rules.after_move(&black_on_hill, Side::White), None);
+        assert_eq!(rules.after_move(&black_on_hill, Side::Black), Some(Outcome::Win(Side::Black)));
+    }
+
+    #[test]
+    fn three_check_is_won_on_the_third_check() {
+        let mut rules = ThreeCheck::default();
+        let check = board("4k3/8/8/8/8/8/4R3/K7");
+        let quiet = board("4k3/8/8/8/8/8/3R4/K7");
+        assert_eq!(rules.after_move(&check, Side::White), None);
+        assert_eq!(rules.after_move(&quiet, Side::White), None);
+        assert_eq!(rules.after_move(&check, Side::White), None);
+        assert_eq!(rules.after_move(&check, Side::White), Some(Outcome::Win(Side::White)));
+    }
+
+    #[test]
+    fn three_check_counts_each_side_and_resets() {
+        let mut rules = ThreeCheck::default();
+        let white_checks = board("4k3/8/8/8/8/8/4R3/K7");
+        let black_checks = board("k7/4r3/8/8/8/8/8/4K3");
+        assert_eq!(rules.after_move(&white_checks, Side::White), None);
+        assert_eq!(rules.after_move(&white_checks, Side::White), None);
+        assert_eq!(rules.after_move(&black_checks, Side::Black), None);
+        rules.reset();
+        assert_eq!(rules.after_move(&white_checks, Side::White), None);
+        assert_eq!(rules.after_move(&white_checks, Side::White), None);
+        assert_eq!(rules.after_move(&black_checks, Side::Black), None);
+        assert_eq!(rules.after_move(&black_checks, Side::Black), None);
+        assert_eq!(rules.after_move(&black_checks, Side::Black), Some(Outcome::Win(Side::Black)));
+    }
+
+    #[test]
+    fn atomic_kings_cannot_capture() {
+        let position = board("4k3/8/8/8/8/8/4p3/4K3");
+        assert!(!Atomic.allows_move(&position, (4, 0), (4, 1)));
+        // Moving to an empty square is fine.
+        assert!(Atomic.allows_move(&position, (4, 0), (3, 0)));
+    }
+
+    #[test]
+    fn atomic_captures_may_not_blow_up_your_own_king() {
+        // Taking on d2 would blow up the king on e1, taking on a1 would not.
+        let position = board("4k3/8/8/8/8/8/3p4/p2RK3");
+        assert!(!Atomic.allows_move(&position, (3, 0), (3, 1)));
+        assert!(Atomic.allows_move(&position, (3, 0), (0, 0)));
+    }
+
+    #[test]
+    fn crazyhouse_pawns_cannot_be_dropped_on_the_end_ranks() {
+        let position = board("4k3/8/8/8/8/8/8/4K3");
+        for side in [Side::White, Side::Black] {
+            assert!(!Crazyhouse.allows_drop(&position, side, PieceType::Pawn, (0, 0)));
+            assert!(!Crazyhouse.allows_drop(&position, side, PieceType::Pawn, (0, 7)));
+            assert!(Crazyhouse.allows_drop(&position, side, PieceType::Pawn, (0, 3)));
+            assert!(Crazyhouse.allows_drop(&position, side, PieceType::Knight, (0, 7)));
+        }
+    }
+}
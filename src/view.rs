@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
+use super::board::*;
+use super::controls::*;
+use super::cursor::update_cursor_pos;
+
+const MAX_ZOOM: f32 = 4.0;
+// Zoom change per wheel notch.
+const ZOOM_STEP: f32 = 0.1;
+// Pixel scrolling (touchpads) counts this many pixels as one notch.
+const PIXELS_PER_LINE: f32 = 100.0;
+
+pub struct ViewPlugin;
+impl Plugin for ViewPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CameraView>()
+            .add_systems(
+                Update,
+                (zoom_view, pan_view, reset_view, apply_view)
+                    .chain()
+                    .after(FitSet)
+                    .before(update_cursor_pos),
+            );
+    }
+}
+
+// Zoom on top of the fitted BoardScale and camera offset from the board's
+// centre. The cursor goes through the camera, so picking pieces and squares
+// keeps working however the view is moved.
+#[derive(Resource)]
+pub struct CameraView {
+    pub zoom: f32,
+    pub pan: Vec2,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        CameraView { zoom: 1.0, pan: Vec2::ZERO }
+    }
+}
+
+impl CameraView {
+    // Keeps the window within the board and its margins.
+    fn clamp(&mut self, size: &BoardSize) {
+        self.zoom = self.zoom.clamp(1.0, MAX_ZOOM);
+        let reach = size.world_size() / 2.0 * (1.0 - 1.0 / self.zoom);
+        self.pan = self.pan.clamp(-reach, reach);
+    }
+}
+
+fn zoom_view(
+    scroll: Res<AccumulatedMouseScroll>,
+    size: Res<BoardSize>,
+    mut view: ResMut<CameraView>,
+) {
+    if scroll.delta.y == 0.0 {
+        return;
+    }
+    let notches = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / PIXELS_PER_LINE,
+    };
+    view.zoom *= (1.0 + ZOOM_STEP).powf(notches);
+    view.clamp(&size);
+}
+
+// Dragging with the middle button moves the board along with the cursor.
+fn pan_view(
+    buttons: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scale: Res<BoardScale>,
+    size: Res<BoardSize>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut view: ResMut<CameraView>,
+) {
+    if !buttons.pressed(MouseButton::Middle) || motion.delta == Vec2::ZERO {
+        return;
+    }
+    let Ok(camera) = camera_query.single() else {
+        return;
+    };
+    // Screen y points down, and a flipped camera turns everything round.
+    let step = Vec3::new(-motion.delta.x, motion.delta.y, 0.0) / (scale.0 * view.zoom);
+    view.pan += (camera.rotation * step).truncate();
+    view.clamp(&size);
+}
+
+// C puts the whole board back in view.
+fn reset_view(keys: Res<ButtonInput<KeyCode>>, bindings: Res<Bindings>, mut view: ResMut<CameraView>) {
+    if bindings.just_pressed(&keys, Action::ResetView) {
+        *view = CameraView::default();
+    }
+}
+
+fn apply_view(
+    view: Res<CameraView>,
+    scale: Res<BoardScale>,
+    mut camera_query: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
+) {
+    if !view.is_changed() && !scale.is_changed() {
+        return;
+    }
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        transform.translation.x = view.pan.x;
+        transform.translation.y = view.pan.y;
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scale = 1.0 / (scale.0 * view.zoom);
+        }
+    }
+}
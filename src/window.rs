@@ -1,5 +1,7 @@
 use bevy::prelude::*; 
 use bevy::window::{PrimaryWindow, Window, WindowMode}; 
+use serde::{Deserialize, Serialize};
+use super::controls::*;
 
 pub struct WindowsPlugin; 
 impl Plugin for WindowsPlugin {
@@ -10,8 +12,8 @@ impl Plugin for WindowsPlugin {
                 medium: Vec2::new(1600.0, 1080.0),
                 small: Vec2::new(1040.0, 1080.0),
             })
-            .add_systems(Startup, set_fullscreen)
-            .add_systems(Update, (set_app_window, toggle_resolution)); 
+            .init_resource::<WindowSettings>()
+            .add_systems(Update, (set_app_window, toggle_resolution, apply_window).chain()); 
     }
 }
 
@@ -22,49 +24,84 @@ pub struct ResolutionSettings{
     small: Vec2, 
 }
 
-fn set_fullscreen(
-    mut window_query: Query<&mut Window, With<PrimaryWindow>>
-) {
-    for mut window in window_query.iter_mut() {
-        window.mode = 
-            WindowMode::BorderlessFullscreen(MonitorSelection::Primary);
+impl ResolutionSettings {
+    fn size(&self, resolution: Resolution) -> Vec2 {
+        match resolution {
+            Resolution::Small => self.small,
+            Resolution::Medium => self.medium,
+            Resolution::Large => self.large,
+        }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum DisplayMode {
+    Windowed,
+    #[default]
+    Fullscreen,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Resolution {
+    Small,
+    Medium,
+    Large,
+}
+
+// How the window should look; apply_window carries changes over to it.
+// Without a resolution the window keeps whatever size it has.
+#[derive(Resource, Clone, Copy, PartialEq, Default)]
+pub struct WindowSettings {
+    pub mode: DisplayMode,
+    pub resolution: Option<Resolution>,
+}
+
 fn set_app_window(
-    mut window_query: Query<&mut Window, With<PrimaryWindow>>, 
+    mut settings: ResMut<WindowSettings>,
     keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<Bindings>,
 ) {
-    if keys.just_pressed(KeyCode::Digit4) {
-        for mut window in window_query.iter_mut() {
-            window.mode = 
-                WindowMode::Windowed; 
-        }
+    if bindings.just_pressed(&keys, Action::Windowed) {
+        settings.mode = DisplayMode::Windowed;
     }
 
-    if keys.just_pressed(KeyCode::Digit5) {
-        for mut window in window_query.iter_mut() {
-            window.mode = 
-                WindowMode::BorderlessFullscreen(MonitorSelection::Primary); 
-        }
+    if bindings.just_pressed(&keys, Action::Fullscreen) {
+        settings.mode = DisplayMode::Fullscreen;
     }
 }
 
 fn toggle_resolution(
     keys: Res<ButtonInput<KeyCode>>,
-    mut window: Single<&mut Window>,
-    resolution: Res<ResolutionSettings>,
+    bindings: Res<Bindings>,
+    mut settings: ResMut<WindowSettings>,
 ) {
-    if keys.just_pressed(KeyCode::Digit1) {
-        let res = resolution.small; 
-        window.resolution.set(res.x, res.y); 
+    if bindings.just_pressed(&keys, Action::SmallWindow) {
+        settings.resolution = Some(Resolution::Small);
+    }
+    if bindings.just_pressed(&keys, Action::MediumWindow) {
+        settings.resolution = Some(Resolution::Medium);
     }
-    if keys.just_pressed(KeyCode::Digit2) {
-        let res = resolution.medium;
-        window.resolution.set(res.x, res.y);
+    if bindings.just_pressed(&keys, Action::LargeWindow) {
+        settings.resolution = Some(Resolution::Large);
+    }
+}
+
+fn apply_window(
+    settings: Res<WindowSettings>,
+    resolution: Res<ResolutionSettings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
     }
-    if keys.just_pressed(KeyCode::Digit3) {
-        let res = resolution.large; 
-        window.resolution.set(res.x, res.y); 
+    for mut window in window_query.iter_mut() {
+        window.mode = match settings.mode {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Fullscreen => WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
+        };
+        if let Some(res) = settings.resolution {
+            let res = resolution.size(res);
+            window.resolution.set(res.x, res.y);
+        }
     }
 }